@@ -1,15 +1,49 @@
-use std::io::Result;
+use std::collections::{BTreeSet, HashSet};
+use std::io::{Read, Result};
 use std::path;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
+use std::time::SystemTime;
 
-use super::counter::Counter;
-use super::threads::{handle, handle_in_thread};
+use super::archive::EntryRules;
+use super::blame::Authorship;
+use super::counter::{Counter, ScanOptions};
+use super::diff::{self, Changes, DiffReport, Side};
+use super::git;
+use super::history::{self, History, Sampling};
+use super::hygiene::HygieneReport;
+use super::ignores::{default_excludes, Globs};
+use super::source::Source;
+use super::summary::Summary;
+use super::symlinks::{FileId, SymlinkPolicy};
+use super::threads::{
+    handle, handle_blame, handle_diff, handle_hygiene, handle_hygiene_reader, handle_in_thread,
+    handle_reader, handle_text_reader, ReadOptions,
+};
+use super::words::{Metric, WordMode};
 
 /// An Application used to count lines programmatically.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct App {
     njobs: usize,
     words: bool,
+    word_mode: WordMode,
+    binary: bool,
+    decompress: bool,
+    archives: bool,
+    ignore: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    default_excludes: Vec<String>,
+    symlinks: SymlinkPolicy,
+    hidden: bool,
+    max_depth: Option<usize>,
+    max_file_size: Option<u64>,
+    one_file_system: bool,
+    newer_than: Option<SystemTime>,
+    older_than: Option<SystemTime>,
+    git_tracked: bool,
+    rev: Option<String>,
+    mailmap: bool,
 }
 
 impl Default for App {
@@ -24,6 +58,24 @@ impl Default for App {
         Self {
             njobs: 1,
             words: false,
+            word_mode: WordMode::default(),
+            binary: false,
+            decompress: false,
+            archives: false,
+            ignore: true,
+            include: vec![],
+            exclude: vec![],
+            default_excludes: default_excludes(),
+            symlinks: SymlinkPolicy::default(),
+            hidden: false,
+            max_depth: None,
+            max_file_size: None,
+            one_file_system: false,
+            newer_than: None,
+            older_than: None,
+            git_tracked: false,
+            rev: None,
+            mailmap: false,
         }
     }
 }
@@ -34,7 +86,7 @@ impl App {
     /// # Arguments
     ///
     /// - `njobs` - The number of jobs ([std::thread::Thread])
-    /// the application should run on.
+    ///   the application should run on.
     ///
     /// - `words` - If true, count words instead of lines.
     ///
@@ -53,11 +105,16 @@ impl App {
     /// let app = xloc::App::new(12, false);
     /// ```
     pub fn new(njobs: usize, words: bool) -> Self {
-        Self { njobs, words }
+        Self {
+            njobs,
+            words,
+            ..Self::default()
+        }
     }
 
     /// Counts the lines/words in a file, or recursively counts the
     /// lines/words in all files if a directory is passed to `path`.
+    /// Binary files are skipped unless [App::set_binary] is enabled.
     ///
     /// # Arguments
     /// - `path` - The path to run this function against.
@@ -65,11 +122,15 @@ impl App {
     /// # Returns
     ///
     /// - [Result<usize, std::io::Error>] - The total line/word count or
-    /// the error, if any.
+    ///   the error, if any.
     ///
     /// # Note
-    /// Currently skips over any files containing non `UTF-8` encoded
-    /// characters, as well as the directories `target` and `.git`.
+    /// Files with a byte order mark are decoded as `UTF-8`, `UTF-16LE`
+    /// or `UTF-16BE`. Files without one that aren't valid `UTF-8` are
+    /// decoded as `Windows-1252`, so legacy 8-bit files are still
+    /// counted. Skips over the [DEFAULT_EXCLUDES](crate::DEFAULT_EXCLUDES)
+    /// like `.git` and `target`, and anything excluded by ignore files,
+    /// see [App::set_default_excludes] and [App::set_ignore].
     ///
     /// Please open an [issue](https://github.com/Jonxslays/xloc/issues)
     /// if you have suggestions for more directories to ignore by
@@ -100,157 +161,1671 @@ impl App {
     /// }
     /// ```
     pub fn count(&self, path: &str) -> Result<usize> {
-        let target = path::PathBuf::from(path);
-        let mut counter = Counter::new(target);
-        let nfiles = counter.count_files()?;
-        let njobs: usize;
-
-        // If only 1 job, no need to even create threads
-        // Otherwise decrement njobs by 1 to save 1 job
-        // for the main thread
-        if self.njobs == 1 {
-            return Ok(self.adjust(handle(counter.files, self.words), nfiles));
-        } else {
-            njobs = self.njobs - 1;
-        }
-
-        // Generate an even distribution of workloads
-        let mut total = 0;
-        let mut position = 0;
-        let workloads = counter.generate_workloads(njobs, nfiles)?;
-        let files = counter.files;
+        Ok(self.summarize(path)?.count)
+    }
 
-        // Create a channel so threads can send data
-        let (tx, rx) = mpsc::channel();
+    /// Counts the lines/words like [App::count], but also reports how
+    /// many files were counted, and every file that was skipped along
+    /// with the [SkipReason](crate::SkipReason).
+    ///
+    /// # Arguments
+    /// - `path` - The path to run this function against.
+    ///
+    /// # Returns
+    ///
+    /// - [Result<Summary, std::io::Error>] - The summary of the count or
+    ///   the error, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let app = xloc::App::default();
+    ///
+    /// if let Ok(summary) = app.summarize(".") {
+    ///     println!("{} lines in {} files", summary.count, summary.files);
+    ///
+    ///     for skipped in summary.skipped {
+    ///         println!("skipped {}: {}", skipped.path.display(), skipped.reason);
+    ///     }
+    /// }
+    /// ```
+    pub fn summarize(&self, path: &str) -> Result<Summary> {
+        self.summarize_all(&[path])
+    }
 
-        // Create a thread for each workload
-        for load in workloads {
-            let start = position;
-            let end = position + load;
-            position = end;
+    /// Counts the lines/words like [App::summarize], treating several
+    /// paths as one logical tree. A file found through more than one
+    /// path, such as `src` and `src/lib`, or through a hard link, is
+    /// only counted once, for the first path that found it.
+    ///
+    /// # Arguments
+    /// - `paths` - The paths to run this function against.
+    ///
+    /// # Returns
+    ///
+    /// - [Result<Summary, std::io::Error>] - The summary of the count,
+    ///   with a subtotal for each path in
+    ///   [Summary::per_root](crate::Summary), or the error, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let app = xloc::App::default();
+    ///
+    /// if let Ok(summary) = app.summarize_all(&["src", "src/lib", "tests"]) {
+    ///     for (root, count) in &summary.per_root {
+    ///         println!("{} lines in {}", count, root.display());
+    ///     }
+    ///
+    ///     println!("{} lines in total", summary.count);
+    /// }
+    /// ```
+    pub fn summarize_all(&self, paths: &[&str]) -> Result<Summary> {
+        let metric = Metric::new(self.words, &self.word_mode)?;
+        let options = self.read_options();
+        let mut summary = Summary::default();
 
-            handle_in_thread(tx.clone(), files[start..end].to_vec(), self.words);
+        for counter in self.scan_all(paths)? {
+            let root = counter.path.clone();
+            let mut subtotal = self.tally(counter, &metric, &options)?;
+            subtotal.per_root.push((root, subtotal.count));
+            summary.add(&subtotal);
         }
 
-        // Drop the final sender, so the receiver doesn't block the main
-        // thread
-        drop(tx);
+        summary.finish();
+        Ok(summary)
+    }
 
-        // Receive the data from the threads
-        for rcvd in rx {
-            total += rcvd;
-        }
+    /// Counts the lines/words in exactly the given files, without
+    /// searching any directories, like a list from `git ls-files` or
+    /// `find -print0`. No ignore files, globs or default excludes are
+    /// applied, directories in the list are left out, and each file is
+    /// counted once even if listed twice or hard linked.
+    ///
+    /// # Arguments
+    /// - `files` - The files to count.
+    ///
+    /// # Returns
+    ///
+    /// - [Result<Summary, std::io::Error>] - The summary of the count or
+    ///   the error, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let app = xloc::App::new(2, false);
+    /// let files = vec!["tests/data/data.rs".into(), "tests/data/data.py".into()];
+    ///
+    /// let summary = app.summarize_files(files).unwrap();
+    ///
+    /// assert_eq!(summary.files, 2);
+    /// ```
+    pub fn summarize_files(&self, files: Vec<path::PathBuf>) -> Result<Summary> {
+        let metric = Metric::new(self.words, &self.word_mode)?;
+        let options = self.read_options();
 
-        Ok(self.adjust(total, nfiles))
+        self.tally(Counter::with_files(dedupe(files)), &metric, &options)
     }
 
-    fn adjust(&self, total: usize, nfiles: usize) -> usize {
-        // If we are counting lines, we need to add 1 to the result for
-        // each file we counted.
-        if !self.words {
-            return total + nfiles;
-        }
-
-        total
+    /// Counts the lines/words in anything that implements [Read], like
+    /// standard input or a network stream.
+    ///
+    /// # Arguments
+    /// - `reader` - The reader to count.
+    ///
+    /// # Returns
+    ///
+    /// - [Result<usize, std::io::Error>] - The line/word count or the
+    ///   error, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let app = xloc::App::new(1, true);
+    /// let count = app.count_reader("Hello there, world!".as_bytes()).unwrap();
+    ///
+    /// assert_eq!(count, 3);
+    /// ```
+    pub fn count_reader<R: Read>(&self, reader: R) -> Result<usize> {
+        Ok(self.summarize_reader(reader, "-")?.count)
     }
 
-    /// Sets the number of jobs ([std::thread::Thread]) the `App` should
-    /// use.
+    /// Counts the lines/words in anything that implements [Read] like
+    /// [App::summarize] does for a file. The `name` is used for it in the
+    /// [Summary], and to detect its compression if
+    /// [App::set_decompress] is enabled. If it is binary, or can't be
+    /// read, it is reported as skipped rather than failing.
     ///
     /// # Arguments
+    /// - `reader` - The reader to count.
+    /// - `name` - The name to report it as, like `-` for stdin.
     ///
-    /// - `njobs` - The new number of jobs.
+    /// # Returns
+    ///
+    /// - [Result<Summary, std::io::Error>] - The summary of the count or
+    ///   the error, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let app = xloc::App::default();
+    /// let summary = app.summarize_reader(std::io::stdin().lock(), "-").unwrap();
+    ///
+    /// println!("{} lines", summary.count);
+    /// ```
+    pub fn summarize_reader<R: Read>(&self, reader: R, name: &str) -> Result<Summary> {
+        let metric = Metric::new(self.words, &self.word_mode)?;
+        let name = path::PathBuf::from(name);
+        let mut summary = handle_reader(reader, name, &metric, &self.read_options());
+
+        self.adjust_summary(&mut summary);
+        Ok(summary)
+    }
+
+    /// Builds a whitespace hygiene report for a file, or recursively for
+    /// all files if a directory is passed to `path`.
+    ///
+    /// The report counts lines with trailing whitespace, tab, space and
+    /// mixed indentation, files that mix indentation styles, and the
+    /// maximum indentation depth, per file and per language.
+    ///
+    /// # Arguments
+    /// - `path` - The path to run this function against.
     ///
     /// # Returns
-    /// - [usize] - The new number of jobs.
+    ///
+    /// - [Result<HygieneReport, std::io::Error>] - The report or the
+    ///   error, if any.
     ///
     /// # Examples
     ///
+    /// ```no_run
+    /// let app = xloc::App::new(4, false);
+    ///
+    /// if let Ok(report) = app.hygiene("src") {
+    ///     println!("{} lines with trailing whitespace", report.total.trailing_whitespace);
+    /// }
     /// ```
-    /// // Creates a new mutable `App` that uses 1 job.
-    /// let mut app = xloc::App::default();
+    pub fn hygiene(&self, path: &str) -> Result<HygieneReport> {
+        self.hygiene_all(&[path])
+    }
+
+    /// Builds a whitespace hygiene report like [App::hygiene], treating
+    /// several paths as one logical tree, so each file is only reported
+    /// once. See [App::summarize_all].
     ///
-    /// assert_eq!(app.get_njobs(), 1);
+    /// # Arguments
+    /// - `paths` - The paths to run this function against.
     ///
-    /// // Sets the number of jobs to 6.
-    /// app.set_njobs(6);
+    /// # Returns
     ///
-    /// assert_eq!(app.get_njobs(), 6);
+    /// - [Result<HygieneReport, std::io::Error>] - The report or the
+    ///   error, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let app = xloc::App::default();
+    ///
+    /// if let Ok(report) = app.hygiene_all(&["src", "tests"]) {
+    ///     print!("{}", report);
+    /// }
     /// ```
-    pub fn set_njobs(&mut self, njobs: usize) -> usize {
-        self.njobs = njobs;
-        njobs
+    pub fn hygiene_all(&self, paths: &[&str]) -> Result<HygieneReport> {
+        let options = self.read_options();
+        let mut report = HygieneReport::default();
+
+        for counter in self.scan_all(paths)? {
+            report.merge(self.inspect(counter, &options)?);
+        }
+
+        Ok(report)
     }
 
-    /// Gets the number of jobs ([std::thread::Thread]) the `App` is
-    /// currently set to use.
+    /// Builds a whitespace hygiene report for anything that implements
+    /// [Read], like [App::hygiene] does for a file. The `name` is used
+    /// for it in the report, and to detect its language and compression.
+    /// If it is binary, or can't be read, it is only reported as skipped.
+    ///
+    /// # Arguments
+    /// - `reader` - The reader to inspect.
+    /// - `name` - The name to report it as, like `-` for stdin.
     ///
     /// # Returns
-    /// - [Result<usize>] - The current number of jobs.
+    ///
+    /// - [HygieneReport] - The report.
     ///
     /// # Examples
     ///
     /// ```
-    /// // Creates a new `App` that uses 3 jobs.
-    /// let app = xloc::App::new(3, false);
+    /// let app = xloc::App::default();
+    /// let report = app.hygiene_reader("fn main() { }  \n".as_bytes(), "main.rs");
     ///
-    /// assert_eq!(app.get_njobs(), 3);
+    /// assert_eq!(report.languages["Rust"].trailing_whitespace, 1);
     /// ```
-    pub fn get_njobs(&self) -> usize {
-        self.njobs
+    pub fn hygiene_reader<R: Read>(&self, reader: R, name: &str) -> HygieneReport {
+        handle_hygiene_reader(reader, path::PathBuf::from(name), &self.read_options())
     }
 
-    /// Gets whether or not we are counting words, instead of lines.
+    /// Attributes the current lines of every file in a path to the
+    /// author who last changed them, using the blame data of the git
+    /// repository the path is in. Lines are reported per author and per
+    /// language, and lines that aren't committed yet are attributed to
+    /// `Not Committed Yet`. The same files are found as [App::summarize]
+    /// would find in the working tree.
+    ///
+    /// # Arguments
+    /// - `path` - The path to run this function against.
     ///
     /// # Returns
-    /// - [bool] - Whether or not we are counting words.
+    ///
+    /// - [Result<Authorship, std::io::Error>] - The breakdown or the
+    ///   error, if any.
     ///
     /// # Examples
     ///
+    /// ```no_run
+    /// let mut app = xloc::App::new(4, false);
+    /// app.set_mailmap(true);
+    ///
+    /// if let Ok(authorship) = app.blame("src") {
+    ///     for (author, lines) in &authorship.authors {
+    ///         println!("{}: {} lines", author, lines);
+    ///     }
+    /// }
     /// ```
-    /// // Creates a new `App`.
+    pub fn blame(&self, path: &str) -> Result<Authorship> {
+        self.blame_all(&[path])
+    }
+
+    /// Attributes lines to authors like [App::blame], treating several
+    /// paths as one logical tree, so each file is only blamed once.
+    ///
+    /// # Arguments
+    /// - `paths` - The paths to run this function against.
+    ///
+    /// # Returns
+    ///
+    /// - [Result<Authorship, std::io::Error>] - The breakdown or the
+    ///   error, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
     /// let app = xloc::App::default();
     ///
-    /// // By default, we do not count words.
-    /// assert_eq!(app.get_words(), false);
+    /// if let Ok(authorship) = app.blame_all(&["src", "tests"]) {
+    ///     print!("{}", authorship);
+    /// }
     /// ```
+    pub fn blame_all(&self, paths: &[&str]) -> Result<Authorship> {
+        let mailmap = self.mailmap;
+        let mut authorship = Authorship::default();
+
+        // Blame needs the files themselves, in a repository
+        let options = ReadOptions {
+            decompress: false,
+            archives: false,
+            ..self.read_options()
+        };
+
+        for path in paths {
+            git::discover(path::Path::new(path))?;
+        }
+
+        let mut seen = HashSet::new();
+
+        for path in paths {
+            let mut counter = self.scan_rev(path, None)?;
+            counter.files.retain(|file| is_unseen(file, &mut seen));
+            authorship.skipped.append(&mut counter.skipped);
+
+            let options = options.clone();
+            let work = move |files| handle_blame(files, &options, mailmap);
+
+            for rcvd in self.dispatch(counter, work)? {
+                authorship.merge(rcvd);
+            }
+        }
+
+        Ok(authorship)
+    }
+
+    /// Builds a whitespace hygiene report for exactly the given files,
+    /// like [App::summarize_files].
+    ///
+    /// # Arguments
+    /// - `files` - The files to report on.
+    ///
+    /// # Returns
+    ///
+    /// - [Result<HygieneReport, std::io::Error>] - The report or the
+    ///   error, if any.
+    ///
+    /// # Examples
     ///
     /// ```
-    /// // Creates a new `App` with 3 jobs, and words set to true.
-    /// let app = xloc::App::new(3, true);
+    /// let app = xloc::App::default();
+    /// let report = app.hygiene_files(vec!["tests/data/data.py".into()]).unwrap();
     ///
-    /// assert_eq!(app.get_words(), true);
+    /// assert_eq!(report.languages["Python"].files, 1);
     /// ```
-    pub fn get_words(&self) -> bool {
-        self.words
+    pub fn hygiene_files(&self, files: Vec<path::PathBuf>) -> Result<HygieneReport> {
+        let options = self.read_options();
+        self.inspect(Counter::with_files(dedupe(files)), &options)
     }
 
-    /// Sets whether or not to count words, instead of lines.
+    /// Reports the code, comment and blank lines added and removed
+    /// between two trees, per file and per language. Each side is a
+    /// directory or, if no such directory exists, a revision of the git
+    /// repository in the current directory, like `v1.2.0` or `HEAD`.
+    /// Files are matched up by their path within each side, and the
+    /// same files are found on each side as [App::summarize] would find.
+    /// Only one pair of files is held in memory at a time per job, and
+    /// between two revisions, files that are the same blob on both sides
+    /// are not read at all.
     ///
     /// # Arguments
+    /// - `old` - The directory or revision to compare from.
+    /// - `new` - The directory or revision to compare to.
     ///
-    /// - `value` - Whether or not to count words.
+    /// # Returns
+    ///
+    /// - [Result<DiffReport, std::io::Error>] - The report or the error,
+    ///   if any.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let app = xloc::App::default();
+    ///
+    /// if let Ok(report) = app.diff("v1.2.0", "HEAD") {
+    ///     println!("{:+} lines of Rust", report.languages["Rust"].code.net());
+    /// }
+    /// ```
+    pub fn diff(&self, old: &str, new: &str) -> Result<DiffReport> {
+        let mut report = DiffReport::default();
+        let old = Arc::new(self.diff_side(old, &mut report)?);
+        let new = Arc::new(self.diff_side(new, &mut report)?);
+
+        // Between two revisions, only the blobs that changed are read
+        let names = old
+            .files
+            .keys()
+            .chain(new.files.keys())
+            .filter(|name| !old.is_same_blob(&new, name))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        let options = self.read_options();
+        let counter = Counter::with_files(names.into_iter().collect());
+
+        for (deltas, mut skipped) in self.dispatch(counter, move |names| {
+            handle_diff(names, &old, &new, &options)
+        })? {
+            report.skipped.append(&mut skipped);
+
+            for (name, delta) in deltas {
+                report.insert(name, delta);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Counts the files of `path` at each sampled commit in the history
+    /// of `rev`, in the repository containing `path`, broken down per
+    /// language. Like [App::set_rev], files are read from the object
+    /// database and only the include and exclude globs apply. Counts
+    /// are cached by blob and tree, so each commit only costs as much as
+    /// what changed in it.
+    ///
+    /// # Arguments
+    /// - `path` - The path in the repository to count.
+    /// - `rev` - The revision whose history is walked, like `HEAD`.
+    /// - `sampling` - Which commits to count.
     ///
     /// # Returns
-    /// - [bool] - The updated state.
+    ///
+    /// - [Result<History, std::io::Error>] - The counts, oldest first,
+    ///   or the error, if any.
     ///
     /// # Examples
     ///
+    /// ```no_run
+    /// let app = xloc::App::default();
+    ///
+    /// if let Ok(history) = app.history(".", "HEAD", xloc::Sampling::Week) {
+    ///     print!("{}", history.to_csv());
+    /// }
     /// ```
-    /// // Creates a new mutable `App`.
-    /// let mut app = xloc::App::default();
+    pub fn history(&self, path: &str, rev: &str, sampling: Sampling) -> Result<History> {
+        let metric = Metric::new(self.words, &self.word_mode)?;
+        let options = self.read_options();
+        let path = path::Path::new(path);
+        let globs = Globs::new(path, &self.include, &self.exclude)?;
+
+        history::walk(
+            path,
+            rev,
+            sampling,
+            self.max_file_size,
+            |file| !globs.is_excluded(file, false),
+            |file, content| {
+                let mut summary = handle_reader(content, file.to_owned(), &metric, &options);
+
+                match summary.skipped.pop() {
+                    Some(skipped) => Err(skipped.reason),
+                    None => Ok(self.adjust(summary.count, 1)),
+                }
+            },
+        )
+    }
+
+    /// Reports the lines added and removed by the changes to `path`
+    /// that are staged in the index, compared to `HEAD`, like
+    /// `git diff --staged`. Only [App::set_include] and
+    /// [App::set_exclude] apply, and files are named relative to `path`.
     ///
-    /// assert_eq!(app.get_words(), false);
+    /// # Arguments
+    /// - `path` - The path in the repository to compare.
     ///
-    /// // Set words to true, so we will now count words.
-    /// app.set_words(true);
+    /// # Returns
     ///
-    /// assert_eq!(app.get_words(), true);
+    /// - [Result<DiffReport, std::io::Error>] - The report or the error,
+    ///   if any.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let app = xloc::App::default();
+    ///
+    /// // Keep commits small in a pre-commit hook.
+    /// if let Ok(report) = app.diff_staged(".") {
+    ///     assert!(report.total.code.added < 400);
+    /// }
     /// ```
-    pub fn set_words(&mut self, value: bool) -> bool {
-        self.words = value;
-        value
+    pub fn diff_staged(&self, path: &str) -> Result<DiffReport> {
+        self.diff_changes(path, Changes::Staged)
     }
+
+    /// Reports the lines added and removed by the changes to `path` in
+    /// the working tree that aren't staged, compared to the index, like
+    /// `git diff`. Untracked files are left out. See [App::diff_staged].
+    ///
+    /// # Arguments
+    /// - `path` - The path in the repository to compare.
+    ///
+    /// # Returns
+    ///
+    /// - [Result<DiffReport, std::io::Error>] - The report or the error,
+    ///   if any.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let app = xloc::App::default();
+    ///
+    /// if let Ok(report) = app.diff_unstaged("src") {
+    ///     print!("{}", report);
+    /// }
+    /// ```
+    pub fn diff_unstaged(&self, path: &str) -> Result<DiffReport> {
+        self.diff_changes(path, Changes::Unstaged)
+    }
+
+    fn diff_changes(&self, path: &str, changes: Changes) -> Result<DiffReport> {
+        let options = self.read_options();
+        let path = path::Path::new(path);
+        let globs = Globs::new(path, &self.include, &self.exclude)?;
+
+        diff::uncommitted(
+            path,
+            changes,
+            self.max_file_size,
+            |file| !globs.is_excluded(file, false),
+            |file, content| handle_text_reader(content, file, &options),
+        )
+    }
+
+    /// Finds the files on one side of a diff, named relative to the
+    /// side, and records the files that were skipped.
+    fn diff_side(&self, side: &str, report: &mut DiffReport) -> Result<Side> {
+        let mut counter = match path::Path::new(side).is_dir() {
+            true => self.scan_rev(side, None)?,
+            false => self.scan_rev(".", Some(side))?,
+        };
+
+        report.skipped.append(&mut counter.skipped);
+
+        let root = counter.path;
+        let files = counter
+            .files
+            .into_iter()
+            .map(|file| (file.strip_prefix(&root).unwrap_or(&file).to_owned(), file))
+            .collect();
+
+        Ok(Side {
+            root,
+            source: counter.source,
+            files,
+            rules: counter.rules,
+        })
+    }
+
+    /// Counts the files a [Counter] found, with its skipped files.
+    fn tally(
+        &self,
+        mut counter: Counter,
+        metric: &Metric,
+        options: &ReadOptions,
+    ) -> Result<Summary> {
+        let metric = metric.clone();
+        let options = ReadOptions {
+            source: counter.source.clone(),
+            rules: counter.rules.clone(),
+            ..options.clone()
+        };
+
+        let mut summary = Summary {
+            skipped: std::mem::take(&mut counter.skipped),
+            ..Summary::default()
+        };
+
+        for rcvd in self.dispatch(counter, move |files| handle(files, &metric, &options))? {
+            summary.add(&rcvd);
+        }
+
+        self.adjust_summary(&mut summary);
+        summary.finish();
+        Ok(summary)
+    }
+
+    fn adjust_summary(&self, summary: &mut Summary) {
+        summary.count = self.adjust(summary.count, summary.files);
+
+        for count in summary.per_file.values_mut() {
+            *count = self.adjust(*count, 1);
+        }
+    }
+
+    /// Builds the hygiene report for the files a [Counter] found, with
+    /// its skipped files.
+    fn inspect(&self, mut counter: Counter, options: &ReadOptions) -> Result<HygieneReport> {
+        let options = ReadOptions {
+            source: counter.source.clone(),
+            rules: counter.rules.clone(),
+            ..options.clone()
+        };
+        let mut report = HygieneReport {
+            skipped: std::mem::take(&mut counter.skipped),
+            ..HygieneReport::default()
+        };
+
+        for rcvd in self.dispatch(counter, move |files| handle_hygiene(files, &options))? {
+            report.merge(rcvd);
+        }
+
+        Ok(report)
+    }
+
+    /// Scans every path, leaving out files that an earlier path, or an
+    /// earlier hard link, already found. Files in a git revision are the
+    /// same if they are at the same path in its tree.
+    fn scan_all(&self, paths: &[&str]) -> Result<Vec<Counter>> {
+        let mut seen = HashSet::new();
+        let mut seen_in_tree = HashSet::new();
+        let mut counters = vec![];
+
+        for path in paths {
+            let mut counter = self.scan(path)?;
+            counter.files.retain(|file| match &counter.source {
+                Source::Filesystem => is_unseen(file, &mut seen),
+                Source::Revision(snapshot) => snapshot
+                    .tree_path(file)
+                    .is_none_or(|inner| seen_in_tree.insert(inner.to_owned())),
+            });
+
+            counters.push(counter);
+        }
+
+        Ok(counters)
+    }
+
+    fn scan(&self, path: &str) -> Result<Counter> {
+        self.scan_rev(path, self.rev.as_deref())
+    }
+
+    /// Scans a path, in the working tree or at a git revision.
+    fn scan_rev(&self, path: &str, rev: Option<&str>) -> Result<Counter> {
+        let mut counter = Counter::new(path::PathBuf::from(path));
+        counter.options = ScanOptions {
+            ignore: self.ignore,
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            default_excludes: self.default_excludes.clone(),
+            symlinks: self.symlinks,
+            hidden: self.hidden,
+            max_depth: self.max_depth,
+            one_file_system: self.one_file_system,
+            archives: self.archives,
+            newer_than: self.newer_than,
+            older_than: self.older_than,
+        };
+
+        match (rev, self.git_tracked) {
+            (Some(rev), _) => counter.count_revision(rev)?,
+            (None, true) => counter.count_tracked()?,
+            (None, false) => counter.count_files()?,
+        };
+
+        Ok(counter)
+    }
+
+    fn read_options(&self) -> ReadOptions {
+        ReadOptions {
+            binary: self.binary,
+            decompress: self.decompress,
+            archives: self.archives,
+            rules: EntryRules {
+                default_excludes: self.default_excludes.clone(),
+                ..EntryRules::default()
+            },
+            source: Source::default(),
+            max_file_size: self.max_file_size,
+        }
+    }
+
+    fn dispatch<T, F>(&self, counter: Counter, work: F) -> Result<Vec<T>>
+    where
+        T: Send + 'static,
+        F: Fn(Vec<path::PathBuf>) -> T + Clone + Send + 'static,
+    {
+        // If only 1 job, no need to even create threads
+        // Otherwise decrement njobs by 1 to save 1 job
+        // for the main thread
+        let njobs = if self.njobs == 1 {
+            return Ok(vec![work(counter.files)]);
+        } else {
+            self.njobs - 1
+        };
+
+        // Generate an even distribution of workloads
+        let mut position = 0;
+        let workloads = counter.generate_workloads(njobs, counter.files.len())?;
+        let files = counter.files;
+
+        // Create a channel so threads can send data
+        let (tx, rx) = mpsc::channel();
+
+        // Create a thread for each workload
+        for load in workloads {
+            let start = position;
+            let end = position + load;
+            position = end;
+
+            handle_in_thread(tx.clone(), files[start..end].to_vec(), work.clone());
+        }
+
+        // Drop the final sender, so the receiver doesn't block the main
+        // thread
+        drop(tx);
+
+        // Receive the data from the threads
+        Ok(rx.into_iter().collect())
+    }
+
+    fn adjust(&self, total: usize, nfiles: usize) -> usize {
+        // If we are counting lines, we need to add 1 to the result for
+        // each file we counted.
+        if !self.words {
+            return total + nfiles;
+        }
+
+        total
+    }
+
+    /// Sets the number of jobs ([std::thread::Thread]) the `App` should
+    /// use.
+    ///
+    /// # Arguments
+    ///
+    /// - `njobs` - The new number of jobs.
+    ///
+    /// # Returns
+    /// - [usize] - The new number of jobs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Creates a new mutable `App` that uses 1 job.
+    /// let mut app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_njobs(), 1);
+    ///
+    /// // Sets the number of jobs to 6.
+    /// app.set_njobs(6);
+    ///
+    /// assert_eq!(app.get_njobs(), 6);
+    /// ```
+    pub fn set_njobs(&mut self, njobs: usize) -> usize {
+        self.njobs = njobs;
+        njobs
+    }
+
+    /// Gets the number of jobs ([std::thread::Thread]) the `App` is
+    /// currently set to use.
+    ///
+    /// # Returns
+    /// - [Result<usize>] - The current number of jobs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Creates a new `App` that uses 3 jobs.
+    /// let app = xloc::App::new(3, false);
+    ///
+    /// assert_eq!(app.get_njobs(), 3);
+    /// ```
+    pub fn get_njobs(&self) -> usize {
+        self.njobs
+    }
+
+    /// Gets whether or not we are counting words, instead of lines.
+    ///
+    /// # Returns
+    /// - [bool] - Whether or not we are counting words.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Creates a new `App`.
+    /// let app = xloc::App::default();
+    ///
+    /// // By default, we do not count words.
+    /// assert_eq!(app.get_words(), false);
+    /// ```
+    ///
+    /// ```
+    /// // Creates a new `App` with 3 jobs, and words set to true.
+    /// let app = xloc::App::new(3, true);
+    ///
+    /// assert_eq!(app.get_words(), true);
+    /// ```
+    pub fn get_words(&self) -> bool {
+        self.words
+    }
+
+    /// Sets whether or not to count words, instead of lines.
+    ///
+    /// # Arguments
+    ///
+    /// - `value` - Whether or not to count words.
+    ///
+    /// # Returns
+    /// - [bool] - The updated state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Creates a new mutable `App`.
+    /// let mut app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_words(), false);
+    ///
+    /// // Set words to true, so we will now count words.
+    /// app.set_words(true);
+    ///
+    /// assert_eq!(app.get_words(), true);
+    /// ```
+    pub fn set_words(&mut self, value: bool) -> bool {
+        self.words = value;
+        value
+    }
+
+    /// Gets the [WordMode] used to split text into words.
+    ///
+    /// # Returns
+    /// - [WordMode] - The current word mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xloc::{App, WordMode};
+    ///
+    /// // By default, the classic xloc word pattern is used.
+    /// let app = App::default();
+    ///
+    /// assert_eq!(app.get_word_mode(), &WordMode::Classic);
+    /// ```
+    pub fn get_word_mode(&self) -> &WordMode {
+        &self.word_mode
+    }
+
+    /// Sets the [WordMode] used to split text into words. This has no
+    /// effect unless we are counting words. An invalid
+    /// [WordMode::Custom] pattern is reported when counting.
+    ///
+    /// # Arguments
+    ///
+    /// - `mode` - The new word mode.
+    ///
+    /// # Returns
+    /// - [WordMode] - The updated word mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xloc::{App, WordMode};
+    ///
+    /// // Count words the way `wc -w` does.
+    /// let mut app = App::new(1, true);
+    /// app.set_word_mode(WordMode::Whitespace);
+    ///
+    /// assert_eq!(app.get_word_mode(), &WordMode::Whitespace);
+    /// ```
+    ///
+    /// ```
+    /// use xloc::{App, WordMode};
+    ///
+    /// // Count only numbers as words.
+    /// let mut app = App::new(1, true);
+    /// app.set_word_mode(WordMode::Custom(r"\d+".to_string()));
+    /// ```
+    pub fn set_word_mode(&mut self, mode: WordMode) -> WordMode {
+        self.word_mode = mode.clone();
+        mode
+    }
+
+    /// Gets whether or not binary files are counted, instead of skipped.
+    ///
+    /// # Returns
+    /// - [bool] - Whether or not binary files are counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, binary files are skipped.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_binary(), false);
+    /// ```
+    pub fn get_binary(&self) -> bool {
+        self.binary
+    }
+
+    /// Sets whether or not to count binary files, instead of skipping
+    /// them. A file is binary if its first block contains a NUL byte or
+    /// mostly control characters.
+    ///
+    /// # Arguments
+    ///
+    /// - `value` - Whether or not to count binary files.
+    ///
+    /// # Returns
+    /// - [bool] - The updated state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Count images, object files and other blobs too.
+    /// app.set_binary(true);
+    ///
+    /// assert_eq!(app.get_binary(), true);
+    /// ```
+    pub fn set_binary(&mut self, value: bool) -> bool {
+        self.binary = value;
+        value
+    }
+
+    /// Gets whether or not compressed files are decompressed and their
+    /// contents counted.
+    ///
+    /// # Returns
+    /// - [bool] - Whether or not compressed files are decompressed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, compressed files are treated like any other file.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_decompress(), false);
+    /// ```
+    pub fn get_decompress(&self) -> bool {
+        self.decompress
+    }
+
+    /// Sets whether or not to decompress `.gz`, `.bz2`, `.xz` and `.zst`
+    /// files on the fly and count their contents. Decompression is
+    /// streamed, so large files never need to fit in memory.
+    ///
+    /// # Arguments
+    ///
+    /// - `value` - Whether or not to decompress compressed files.
+    ///
+    /// # Returns
+    /// - [bool] - The updated state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Count the lines inside `access.log.gz`, not the compressed bytes.
+    /// app.set_decompress(true);
+    ///
+    /// assert_eq!(app.get_decompress(), true);
+    /// ```
+    pub fn set_decompress(&mut self, value: bool) -> bool {
+        self.decompress = value;
+        value
+    }
+
+    /// Gets whether or not archives are walked like directories.
+    ///
+    /// # Returns
+    /// - [bool] - Whether or not archives are walked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, archives are treated like any other file.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_archives(), false);
+    /// ```
+    pub fn get_archives(&self) -> bool {
+        self.archives
+    }
+
+    /// Sets whether or not to treat `.tar`, `.tar.gz`, `.tgz` and `.zip`
+    /// files (and tarballs compressed with `.bz2`, `.xz` or `.zst`) as
+    /// directories, counting each file inside them. Entries are named
+    /// like `release.tar!/src/main.rs` in the [Summary], and the same
+    /// directories are excluded inside archives as outside of them.
+    ///
+    /// # Arguments
+    ///
+    /// - `value` - Whether or not to walk archives.
+    ///
+    /// # Returns
+    /// - [bool] - The updated state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Size a release tarball without unpacking it.
+    /// app.set_archives(true);
+    ///
+    /// assert_eq!(app.get_archives(), true);
+    /// ```
+    pub fn set_archives(&mut self, value: bool) -> bool {
+        self.archives = value;
+        value
+    }
+
+    /// Gets whether or not ignore files are respected.
+    ///
+    /// # Returns
+    /// - [bool] - Whether or not ignore files are respected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, ignored files are left out of the count.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_ignore(), true);
+    /// ```
+    pub fn get_ignore(&self) -> bool {
+        self.ignore
+    }
+
+    /// Sets whether or not to respect `.gitignore`, `.ignore` and
+    /// `.xlocignore` files in every scanned directory and the ones above
+    /// it up to the repository root, along with `.git/info/exclude` and
+    /// the global git excludes file. Rules in `.xlocignore` win over
+    /// `.ignore`, which wins over `.gitignore`. Paths passed directly to
+    /// [App::count] are always counted.
+    ///
+    /// # Arguments
+    ///
+    /// - `value` - Whether or not to respect ignore files.
+    ///
+    /// # Returns
+    /// - [bool] - The updated state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Count build outputs and caches too.
+    /// app.set_ignore(false);
+    ///
+    /// assert_eq!(app.get_ignore(), false);
+    /// ```
+    pub fn set_ignore(&mut self, value: bool) -> bool {
+        self.ignore = value;
+        value
+    }
+
+    /// Gets the globs a file must match one of to be counted.
+    ///
+    /// # Returns
+    /// - [&[String]] - The include globs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, every file is counted.
+    /// let app = xloc::App::default();
+    ///
+    /// assert!(app.get_include().is_empty());
+    /// ```
+    pub fn get_include(&self) -> &[String] {
+        &self.include
+    }
+
+    /// Sets the globs a file must match one of to be counted. Globs use
+    /// `.gitignore` syntax, relative to the path being counted: `*.rs`
+    /// matches a file name at any depth, `src/**` matches a full path,
+    /// and a leading `!` negates a glob. Directories are always
+    /// searched. An invalid glob is reported when counting.
+    ///
+    /// # Arguments
+    ///
+    /// - `globs` - The include globs.
+    ///
+    /// # Returns
+    /// - [Vec<String>] - The updated include globs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Only count Rust and TOML files.
+    /// app.set_include(vec!["*.rs".to_string(), "*.toml".to_string()]);
+    ///
+    /// assert_eq!(app.get_include().len(), 2);
+    /// ```
+    pub fn set_include(&mut self, globs: Vec<String>) -> Vec<String> {
+        self.include = globs.clone();
+        globs
+    }
+
+    /// Gets the globs for files and directories to leave out.
+    ///
+    /// # Returns
+    /// - [&[String]] - The exclude globs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let app = xloc::App::default();
+    ///
+    /// assert!(app.get_exclude().is_empty());
+    /// ```
+    pub fn get_exclude(&self) -> &[String] {
+        &self.exclude
+    }
+
+    /// Sets the globs for files and directories to leave out, with the
+    /// same syntax as [App::set_include]. When several globs match a
+    /// path the last one wins, so `!docs/README.md` after `docs/**`
+    /// keeps that one file.
+    ///
+    /// # Arguments
+    ///
+    /// - `globs` - The exclude globs.
+    ///
+    /// # Returns
+    /// - [Vec<String>] - The updated exclude globs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Leave out the docs and any generated sources.
+    /// app.set_exclude(vec!["docs/**".to_string(), "*.generated.rs".to_string()]);
+    ///
+    /// assert_eq!(app.get_exclude()[0], "docs/**");
+    /// ```
+    pub fn set_exclude(&mut self, globs: Vec<String>) -> Vec<String> {
+        self.exclude = globs.clone();
+        globs
+    }
+
+    /// Gets the names of files and directories that are left out
+    /// wherever they are found.
+    ///
+    /// # Returns
+    /// - [&[String]] - The default excludes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, these are the DEFAULT_EXCLUDES.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_default_excludes(), xloc::DEFAULT_EXCLUDES);
+    /// ```
+    pub fn get_default_excludes(&self) -> &[String] {
+        &self.default_excludes
+    }
+
+    /// Sets the names of files and directories that are left out
+    /// wherever they are found, including inside archives. These are
+    /// plain names rather than globs, and replace the
+    /// [DEFAULT_EXCLUDES](crate::DEFAULT_EXCLUDES) entirely.
+    ///
+    /// # Arguments
+    ///
+    /// - `names` - The names to exclude.
+    ///
+    /// # Returns
+    /// - [Vec<String>] - The updated default excludes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Count everything but .git, including target and node_modules.
+    /// app.set_default_excludes(vec![".git".to_string()]);
+    ///
+    /// // Or don't exclude anything by default.
+    /// app.set_default_excludes(vec![]);
+    ///
+    /// assert!(app.get_default_excludes().is_empty());
+    /// ```
+    pub fn set_default_excludes(&mut self, names: Vec<String>) -> Vec<String> {
+        self.default_excludes = names.clone();
+        names
+    }
+
+    /// Gets the [SymlinkPolicy] for symlinks found while scanning.
+    ///
+    /// # Returns
+    /// - [SymlinkPolicy] - The symlink policy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xloc::{App, SymlinkPolicy};
+    ///
+    /// // By default, every symlink is followed, skipping loops.
+    /// let app = App::default();
+    ///
+    /// assert_eq!(app.get_symlinks(), SymlinkPolicy::Always);
+    /// ```
+    pub fn get_symlinks(&self) -> SymlinkPolicy {
+        self.symlinks
+    }
+
+    /// Sets the [SymlinkPolicy] for symlinks found while scanning.
+    /// Followed symlinks that lead back to a directory being scanned
+    /// are skipped as a [SkipReason::SymlinkLoop](crate::SkipReason),
+    /// and symlinks to nothing as a
+    /// [SkipReason::BrokenLink](crate::SkipReason).
+    ///
+    /// # Arguments
+    ///
+    /// - `policy` - The symlink policy.
+    ///
+    /// # Returns
+    /// - [SymlinkPolicy] - The updated policy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xloc::{App, SymlinkPolicy};
+    ///
+    /// let mut app = App::default();
+    ///
+    /// // Follow symlinks, unless they point outside the counted dir.
+    /// app.set_symlinks(SymlinkPolicy::WithinRoot);
+    ///
+    /// assert_eq!(app.get_symlinks(), SymlinkPolicy::WithinRoot);
+    /// ```
+    pub fn set_symlinks(&mut self, policy: SymlinkPolicy) -> SymlinkPolicy {
+        self.symlinks = policy;
+        policy
+    }
+
+    /// Gets whether or not hidden files and directories are counted.
+    ///
+    /// # Returns
+    /// - [bool] - Whether or not hidden files are counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, hidden files are skipped.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_hidden(), false);
+    /// ```
+    pub fn get_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Sets whether or not to count hidden files, and search hidden
+    /// directories, whose names start with a `.` like `.env` or
+    /// `.cache`. Paths passed to [App::count] are always counted.
+    ///
+    /// # Arguments
+    ///
+    /// - `value` - Whether or not to count hidden files.
+    ///
+    /// # Returns
+    /// - [bool] - The updated state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Count dotfiles like .github/workflows too.
+    /// app.set_hidden(true);
+    ///
+    /// assert_eq!(app.get_hidden(), true);
+    /// ```
+    pub fn set_hidden(&mut self, value: bool) -> bool {
+        self.hidden = value;
+        value
+    }
+
+    /// Gets how many directories deep to search, if limited.
+    ///
+    /// # Returns
+    /// - [Option<usize>] - The maximum depth, or [None] for no limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, there is no limit.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_max_depth(), None);
+    /// ```
+    pub fn get_max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Sets how many directories deep to search below the path being
+    /// counted, like `find -maxdepth`. A depth of `1` only counts the
+    /// files directly inside it, and `0` counts nothing unless the path
+    /// is a file.
+    ///
+    /// # Arguments
+    ///
+    /// - `depth` - The maximum depth, or [None] for no limit.
+    ///
+    /// # Returns
+    /// - [Option<usize>] - The updated maximum depth.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Count the top level of a monorepo, and one level of packages.
+    /// app.set_max_depth(Some(2));
+    ///
+    /// assert_eq!(app.get_max_depth(), Some(2));
+    /// ```
+    pub fn set_max_depth(&mut self, depth: Option<usize>) -> Option<usize> {
+        self.max_depth = depth;
+        depth
+    }
+
+    /// Gets the size in bytes above which files are skipped, if limited.
+    ///
+    /// # Returns
+    /// - [Option<u64>] - The maximum file size, or [None] for no limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, files of any size are counted.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_max_file_size(), None);
+    /// ```
+    pub fn get_max_file_size(&self) -> Option<u64> {
+        self.max_file_size
+    }
+
+    /// Sets the size in bytes above which files are skipped, without
+    /// being read, and reported as [SkipReason::TooLarge]. The
+    /// size is the one on disk, before any decompression. Files under
+    /// the limit are still counted in bounded chunks, however large.
+    ///
+    /// # Arguments
+    ///
+    /// - `size` - The maximum file size, or [None] for no limit.
+    ///
+    /// # Returns
+    /// - [Option<u64>] - The updated maximum file size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Leave out stray logs and dumps larger than 10 MiB.
+    /// app.set_max_file_size(Some(10 * 1024 * 1024));
+    ///
+    /// assert_eq!(app.get_max_file_size(), Some(10485760));
+    /// ```
+    pub fn set_max_file_size(&mut self, size: Option<u64>) -> Option<u64> {
+        self.max_file_size = size;
+        size
+    }
+
+    /// Gets the time files must be last modified after to be counted, if
+    /// any.
+    ///
+    /// # Returns
+    /// - [Option<SystemTime>] - The time, or [None] for no limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, files modified at any time are counted.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_newer_than(), None);
+    /// ```
+    pub fn get_newer_than(&self) -> Option<SystemTime> {
+        self.newer_than
+    }
+
+    /// Sets the time files must be last modified after to be counted,
+    /// like `find -newermt`. The filter applies to files found while
+    /// scanning, or with [App::set_git_tracked], but not to a revision
+    /// set with [App::set_rev] or a list of files that is already known.
+    ///
+    /// # Arguments
+    ///
+    /// - `time` - The time, or [None] for no limit.
+    ///
+    /// # Returns
+    /// - [Option<SystemTime>] - The updated time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Count the files touched in the last 30 days.
+    /// let month = SystemTime::now() - Duration::from_secs(30 * 86_400);
+    /// app.set_newer_than(Some(month));
+    ///
+    /// assert_eq!(app.get_newer_than(), Some(month));
+    /// ```
+    pub fn set_newer_than(&mut self, time: Option<SystemTime>) -> Option<SystemTime> {
+        self.newer_than = time;
+        time
+    }
+
+    /// Gets the time files must be last modified before to be counted,
+    /// if any.
+    ///
+    /// # Returns
+    /// - [Option<SystemTime>] - The time, or [None] for no limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, files modified at any time are counted.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_older_than(), None);
+    /// ```
+    pub fn get_older_than(&self) -> Option<SystemTime> {
+        self.older_than
+    }
+
+    /// Sets the time files must be last modified before to be counted.
+    /// Like [App::set_newer_than], it only applies to files found on
+    /// disk, and the two together count the files modified in between.
+    ///
+    /// # Arguments
+    ///
+    /// - `time` - The time, or [None] for no limit.
+    ///
+    /// # Returns
+    /// - [Option<SystemTime>] - The updated time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Count the files nobody has touched in a year.
+    /// let year = SystemTime::now() - Duration::from_secs(365 * 86_400);
+    /// app.set_older_than(Some(year));
+    ///
+    /// assert_eq!(app.get_older_than(), Some(year));
+    /// ```
+    pub fn set_older_than(&mut self, time: Option<SystemTime>) -> Option<SystemTime> {
+        self.older_than = time;
+        time
+    }
+
+    /// Gets whether or not the scan stays on one filesystem.
+    ///
+    /// # Returns
+    /// - [bool] - Whether or not the scan stays on one filesystem.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, mount points are searched.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_one_file_system(), false);
+    /// ```
+    pub fn get_one_file_system(&self) -> bool {
+        self.one_file_system
+    }
+
+    /// Sets whether or not to stay on the filesystem of the path being
+    /// counted, leaving out network mounts, FUSE filesystems and other
+    /// devices mounted below it, like `find -xdev`. On Linux, pseudo
+    /// filesystems like proc and sysfs are always left out when found
+    /// below the path, wherever they are mounted.
+    ///
+    /// # Arguments
+    ///
+    /// - `value` - Whether or not to stay on one filesystem.
+    ///
+    /// # Returns
+    /// - [bool] - The updated state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Count a home directory, without the mounted network share.
+    /// app.set_one_file_system(true);
+    ///
+    /// assert_eq!(app.get_one_file_system(), true);
+    /// ```
+    pub fn set_one_file_system(&mut self, value: bool) -> bool {
+        self.one_file_system = value;
+        value
+    }
+
+    /// Gets whether or not only files tracked by git are counted.
+    ///
+    /// # Returns
+    /// - [bool] - Whether or not only tracked files are counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, directories are scanned for files.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_git_tracked(), false);
+    /// ```
+    pub fn get_git_tracked(&self) -> bool {
+        self.git_tracked
+    }
+
+    /// Sets whether or not to count exactly the files tracked in the
+    /// git index of the repository containing the path, instead of
+    /// scanning it, so untracked files in a dirty checkout are left out.
+    /// Worktrees use their own index, and tracked files in checked out
+    /// submodules are counted too. Only [App::set_include] and
+    /// [App::set_exclude] still apply. Counting fails with
+    /// [std::io::ErrorKind::NotFound] outside of a repository.
+    ///
+    /// # Arguments
+    ///
+    /// - `value` - Whether or not to count only tracked files.
+    ///
+    /// # Returns
+    /// - [bool] - The updated state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Count what is committed or staged, not build outputs.
+    /// app.set_git_tracked(true);
+    ///
+    /// assert_eq!(app.get_git_tracked(), true);
+    /// ```
+    pub fn set_git_tracked(&mut self, value: bool) -> bool {
+        self.git_tracked = value;
+        value
+    }
+
+    /// Gets the git revision whose files are counted, if any.
+    ///
+    /// # Returns
+    /// - [Option<&str>] - The revision, or [None] for the working tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, the files on disk are counted.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_rev(), None);
+    /// ```
+    pub fn get_rev(&self) -> Option<&str> {
+        self.rev.as_deref()
+    }
+
+    /// Sets the git revision to count the files of, instead of the
+    /// working tree. This is a commit, tag, branch or anything else `git
+    /// rev-parse` accepts, and the files are read from the repository's
+    /// object database, so nothing is checked out. Symlinks and
+    /// submodules in the revision are left out, and only
+    /// [App::set_include] and [App::set_exclude] still apply. Counting
+    /// fails with [std::io::ErrorKind::NotFound] if the revision or the
+    /// path within it doesn't exist.
+    ///
+    /// # Arguments
+    ///
+    /// - `rev` - The revision, or [None] to count the working tree.
+    ///
+    /// # Returns
+    /// - [Option<String>] - The updated revision.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// // Size an old release.
+    /// app.set_rev(Some("v1.2.0".to_string()));
+    ///
+    /// assert_eq!(app.get_rev(), Some("v1.2.0"));
+    /// ```
+    pub fn set_rev(&mut self, rev: Option<String>) -> Option<String> {
+        self.rev = rev.clone();
+        rev
+    }
+
+    /// Gets whether or not the repository's `.mailmap` is applied to
+    /// authors in [App::blame].
+    ///
+    /// # Returns
+    /// - [bool] - Whether or not the mailmap is applied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // By default, authors are named as they were in each commit.
+    /// let app = xloc::App::default();
+    ///
+    /// assert_eq!(app.get_mailmap(), false);
+    /// ```
+    pub fn get_mailmap(&self) -> bool {
+        self.mailmap
+    }
+
+    /// Sets whether or not to apply the repository's `.mailmap` to the
+    /// authors in [App::blame], so one person committing under several
+    /// names or emails is reported once, by their canonical name.
+    ///
+    /// # Arguments
+    ///
+    /// - `value` - Whether or not to apply the mailmap.
+    ///
+    /// # Returns
+    /// - [bool] - The updated state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut app = xloc::App::default();
+    ///
+    /// app.set_mailmap(true);
+    ///
+    /// assert_eq!(app.get_mailmap(), true);
+    /// ```
+    pub fn set_mailmap(&mut self, value: bool) -> bool {
+        self.mailmap = value;
+        value
+    }
+}
+
+/// Leaves out directories and files already in the list, by path or
+/// through a hard link.
+fn dedupe(files: Vec<path::PathBuf>) -> Vec<path::PathBuf> {
+    let mut seen = HashSet::new();
+
+    files
+        .into_iter()
+        .filter(|file| !file.is_dir() && is_unseen(file, &mut seen))
+        .collect()
+}
+
+/// Whether a file hasn't been seen yet, by path or through a hard link.
+/// Files that can't be identified are kept, so the reason they can't be
+/// read is reported when they are counted.
+fn is_unseen(file: &path::Path, seen: &mut HashSet<FileId>) -> bool {
+    FileId::of(file).ok().is_none_or(|id| seen.insert(id))
 }
 
 #[cfg(test)]
@@ -0,0 +1,331 @@
+use std::io::{Error, ErrorKind, Read, Result, Seek};
+use std::path::{Path, PathBuf};
+
+use super::compression::Compression;
+use super::ignores::{self, Globs, Ignores};
+
+/// The separator between an archive's path and the path of an entry
+/// inside it, as in `release.tar!/src/main.rs`.
+pub const ENTRY_SEPARATOR: &str = "!/";
+
+/// An archive format xloc can walk like a directory.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Archive {
+    /// A tarball, optionally compressed as a whole.
+    Tar(Option<Compression>),
+    Zip,
+}
+
+impl Archive {
+    /// Detects the archive format of a file from its extension.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+
+        if name.ends_with(".zip") {
+            return Some(Self::Zip);
+        }
+
+        if name.ends_with(".tar") {
+            return Some(Self::Tar(None));
+        }
+
+        if name.ends_with(".tgz") {
+            return Some(Self::Tar(Some(Compression::Gzip)));
+        }
+
+        match Compression::detect(path) {
+            Some(format) if name[..name.rfind('.')?].ends_with(".tar") => {
+                Some(Self::Tar(Some(format)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Calls `f` with the virtual path and contents of every file in the
+    /// archive at `path`, whose contents are `file`, skipping the entries
+    /// the `rules` leave out.
+    pub fn for_each_entry<R, F>(
+        self,
+        file: R,
+        path: &Path,
+        rules: &EntryRules,
+        mut f: F,
+    ) -> Result<()>
+    where
+        R: Read + Seek,
+        F: FnMut(PathBuf, &mut dyn Read),
+    {
+        let filter = Filter::new(path, rules)?;
+
+        match self {
+            Self::Tar(compression) => {
+                let reader: Box<dyn Read + '_> = match compression {
+                    Some(format) => format.decoder(file)?,
+                    None => Box::new(file),
+                };
+
+                let mut tar = tar::Archive::new(reader);
+
+                for entry in tar.entries()? {
+                    let mut entry = entry?;
+
+                    if !entry.header().entry_type().is_file() {
+                        continue;
+                    }
+
+                    let inner = entry.path()?.into_owned();
+
+                    if filter.is_included(&inner) {
+                        f(entry_path(path, &inner), &mut entry);
+                    }
+                }
+            }
+            Self::Zip => {
+                let mut zip = zip::ZipArchive::new(file).map_err(to_io_error)?;
+
+                for i in 0..zip.len() {
+                    let mut entry = zip.by_index(i).map_err(to_io_error)?;
+
+                    let inner = match entry.enclosed_name() {
+                        Some(inner) if entry.is_file() => inner,
+                        _ => continue,
+                    };
+
+                    if filter.is_included(&inner) {
+                        f(entry_path(path, &inner), &mut entry);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The rules a [Counter](crate::counter::Counter) applied to the files
+/// it found, which are applied the same way to the entries of archives
+/// among them, as if each archive were a directory. The default rules
+/// leave nothing out.
+#[derive(Debug, Clone, Default)]
+pub struct EntryRules {
+    /// The root of the scan, which the globs are relative to.
+    pub root: PathBuf,
+
+    /// Globs an entry must match one of, see [Globs].
+    pub include: Vec<String>,
+
+    /// Globs for entries to leave out, see [Globs].
+    pub exclude: Vec<String>,
+
+    /// Names of files and directories to leave out wherever they are.
+    pub default_excludes: Vec<String>,
+
+    /// Whether the ignore files around the archive apply to its entries.
+    pub ignore: bool,
+
+    /// Whether to leave out hidden entries and directories.
+    pub skip_hidden: bool,
+
+    /// How many directories deep to search below the root, counting
+    /// each archive as a directory.
+    pub max_depth: Option<usize>,
+}
+
+/// The [EntryRules] for the entries of one archive.
+struct Filter<'a> {
+    archive: &'a Path,
+    rules: &'a EntryRules,
+    globs: Globs,
+    ignores: Ignores,
+    depth: usize,
+}
+
+impl<'a> Filter<'a> {
+    fn new(archive: &'a Path, rules: &'a EntryRules) -> Result<Self> {
+        let globs = match rules.include.is_empty() && rules.exclude.is_empty() {
+            true => Globs::default(),
+            false => Globs::new(&rules.root, &rules.include, &rules.exclude)?,
+        };
+
+        // The rules of every directory down to the one with the archive
+        let ignores = match archive.parent() {
+            Some(dir) if rules.ignore => {
+                let dir = match dir.as_os_str().is_empty() {
+                    true => Path::new("."),
+                    false => dir,
+                };
+
+                Ignores::root(dir).descend(dir)
+            }
+            _ => Ignores::default(),
+        };
+
+        // An archive directly inside the root is as deep as a directory
+        // there, and the root itself is not inside anything
+        let depth = archive
+            .strip_prefix(&rules.root)
+            .map_or(1, |inner| inner.components().count());
+
+        Ok(Self {
+            archive,
+            rules,
+            globs,
+            ignores,
+            depth,
+        })
+    }
+
+    /// Whether an entry should be counted, checking each directory it
+    /// is in and then the entry itself, like a scan of real directories.
+    fn is_included(&self, inner: &Path) -> bool {
+        let components = inner.components().count();
+
+        if self
+            .rules
+            .max_depth
+            .is_some_and(|max| self.depth + components > max)
+        {
+            return false;
+        }
+
+        let mut dirs = inner.ancestors().collect::<Vec<_>>();
+        dirs.pop();
+
+        dirs.iter().rev().enumerate().all(|(i, dir)| {
+            let is_dir = i + 1 < components;
+            let path = entry_path(self.archive, dir);
+
+            (!self.rules.skip_hidden || !ignores::is_hidden(dir))
+                && !ignores::is_excluded_name(dir, &self.rules.default_excludes)
+                && !self.ignores.is_ignored(&path, is_dir)
+                && !self.globs.is_excluded(&path, is_dir)
+        })
+    }
+}
+
+fn entry_path(archive: &Path, inner: &Path) -> PathBuf {
+    let mut path = archive.as_os_str().to_owned();
+    path.push(ENTRY_SEPARATOR);
+    path.push(inner.as_os_str());
+    PathBuf::from(path)
+}
+
+fn to_io_error(e: zip::result::ZipError) -> Error {
+    match e {
+        zip::result::ZipError::Io(e) => e,
+        e => Error::new(ErrorKind::InvalidData, e),
+    }
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use std::fs::File;
+    use std::path::{Path, PathBuf};
+
+    use super::{Archive, EntryRules};
+    use crate::compression::Compression;
+
+    fn entries(path: &str) -> Vec<(PathBuf, String)> {
+        entries_with(path, &EntryRules::default())
+    }
+
+    fn entries_with(path: &str, rules: &EntryRules) -> Vec<(PathBuf, String)> {
+        let path = Path::new(path);
+        let mut entries = vec![];
+
+        Archive::detect(path)
+            .unwrap()
+            .for_each_entry(File::open(path).unwrap(), path, rules, |name, reader| {
+                let mut text = String::new();
+                reader.read_to_string(&mut text).unwrap();
+                entries.push((name, text));
+            })
+            .unwrap();
+
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn archive_detect() {
+        let detect = |p: &str| Archive::detect(Path::new(p));
+
+        assert_eq!(detect("a.tar"), Some(Archive::Tar(None)));
+        assert_eq!(detect("a.TGZ"), Some(Archive::Tar(Some(Compression::Gzip))));
+        assert_eq!(
+            detect("a.tar.zst"),
+            Some(Archive::Tar(Some(Compression::Zstd)))
+        );
+        assert_eq!(detect("a.zip"), Some(Archive::Zip));
+        assert_eq!(detect("a.log.gz"), None);
+        assert_eq!(detect("a.rs"), None);
+    }
+
+    #[test]
+    fn archive_tar_entries() {
+        let entries = entries("tests/archives/release.tar");
+        let names = entries.iter().map(|e| e.0.clone()).collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            vec![
+                PathBuf::from("tests/archives/release.tar!/release/README.md"),
+                PathBuf::from("tests/archives/release.tar!/release/src/main.rs"),
+            ]
+        );
+        assert_eq!(entries[1].1.lines().count(), 3);
+    }
+
+    #[test]
+    fn archive_compressed_tar_and_zip_entries() {
+        assert_eq!(entries("tests/archives/release.tar.gz").len(), 2);
+        assert_eq!(entries("tests/archives/release.tgz").len(), 2);
+        assert_eq!(entries("tests/archives/release.zip").len(), 2);
+    }
+
+    #[test]
+    fn archive_entry_rules() {
+        let names = |rules: EntryRules| {
+            entries_with("tests/archives/release.tar", &rules)
+                .into_iter()
+                .map(|e| e.0)
+                .collect::<Vec<_>>()
+        };
+        let main = PathBuf::from("tests/archives/release.tar!/release/src/main.rs");
+        let rules = EntryRules {
+            root: PathBuf::from("tests"),
+            ..EntryRules::default()
+        };
+
+        let include = EntryRules {
+            include: vec!["*.rs".to_string()],
+            ..rules.clone()
+        };
+        assert_eq!(names(include), vec![main.clone()]);
+
+        let exclude = EntryRules {
+            exclude: vec!["src".to_string()],
+            ..rules.clone()
+        };
+        assert_eq!(names(exclude).len(), 1);
+
+        let default_excludes = EntryRules {
+            default_excludes: vec!["src".to_string()],
+            ..rules.clone()
+        };
+        assert!(!names(default_excludes).contains(&main));
+
+        // archives/release.tar!/release/src/main.rs is 5 deep
+        let shallow = EntryRules {
+            max_depth: Some(4),
+            ..rules.clone()
+        };
+        assert_eq!(names(shallow).len(), 1);
+
+        let deep = EntryRules {
+            max_depth: Some(5),
+            ..rules
+        };
+        assert_eq!(names(deep).len(), 2);
+    }
+}
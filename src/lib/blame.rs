@@ -0,0 +1,285 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use git2::{BlameOptions, ErrorCode, Oid, Repository};
+
+use super::git::to_io_error;
+use super::summary::Skipped;
+
+/// The author lines are attributed to when they aren't committed yet,
+/// like `git blame` does.
+pub const UNCOMMITTED: &str = "Not Committed Yet";
+
+/// Lines attributed to each author, broken down per language.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Authorship {
+    /// The lines of each author, keyed by `Name <email>`.
+    pub authors: BTreeMap<String, usize>,
+
+    /// The lines of each author in each language, keyed by language
+    /// name and then by author.
+    pub languages: BTreeMap<String, BTreeMap<String, usize>>,
+
+    /// Every line that was attributed.
+    pub total: usize,
+
+    /// Files that couldn't be blamed, and why.
+    pub skipped: Vec<Skipped>,
+}
+
+impl Authorship {
+    /// Records the lines of each author in a single file.
+    pub fn insert(&mut self, language: &str, authors: BTreeMap<String, usize>) {
+        let by_author = self.languages.entry(language.to_string()).or_default();
+
+        for (author, lines) in authors {
+            *by_author.entry(author.clone()).or_default() += lines;
+            *self.authors.entry(author).or_default() += lines;
+            self.total += lines;
+        }
+    }
+
+    /// Merges another breakdown into this one.
+    pub fn merge(&mut self, other: Self) {
+        for (language, authors) in other.languages {
+            self.insert(&language, authors);
+        }
+
+        self.skipped.extend(other.skipped);
+    }
+}
+
+/// Attributes each line of a file's current `content` to the author who
+/// last changed it, with the repository's `.mailmap` applied if
+/// `mailmap` is set. Lines that differ from the last commit, or files
+/// that were never committed, are attributed to [UNCOMMITTED].
+///
+/// The raw `content` is compared with the committed bytes, so files in
+/// any encoding are blamed, while its decoded `text` only counts the
+/// lines of files with nothing committed to compare with.
+pub fn blame(
+    file: &Path,
+    content: &[u8],
+    text: &str,
+    mailmap: bool,
+) -> Result<BTreeMap<String, usize>> {
+    let file = fs::canonicalize(file)?;
+    let dir = file.parent().unwrap_or(&file);
+    let repo = Repository::discover(dir).map_err(to_io_error)?;
+
+    let workdir = match repo.workdir() {
+        Some(workdir) => fs::canonicalize(workdir)?,
+        None => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Bare repositories have no files to blame",
+            ))
+        }
+    };
+
+    let inner = file
+        .strip_prefix(&workdir)
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "File is outside of its repository"))?;
+
+    let mut options = BlameOptions::new();
+    options.use_mailmap(mailmap);
+
+    let mut authors = BTreeMap::new();
+
+    // Before the first commit, HEAD doesn't point at anything to blame
+    let blamed = repo
+        .head()
+        .and_then(|_| repo.blame_file(inner, Some(&mut options)));
+
+    let committed = match blamed {
+        Ok(committed) => committed,
+        Err(e) if matches!(e.code(), ErrorCode::NotFound | ErrorCode::UnbornBranch) => {
+            authors.insert(UNCOMMITTED.to_string(), text.lines().count());
+            return Ok(authors);
+        }
+        Err(e) => return Err(to_io_error(e)),
+    };
+
+    let current = committed.blame_buffer(content).map_err(to_io_error)?;
+
+    let mailmap = match mailmap {
+        true => Some(repo.mailmap().map_err(to_io_error)?),
+        false => None,
+    };
+
+    // Hunks split up by the buffer have no signature of their own, so
+    // the author is read from each commit instead, once per commit
+    let mut names: HashMap<Oid, String> = HashMap::new();
+
+    for hunk in current.iter() {
+        let id = hunk.final_commit_id();
+
+        let author = match names.get(&id) {
+            Some(author) => author.clone(),
+            None if id.is_zero() => UNCOMMITTED.to_string(),
+            None => {
+                let commit = repo.find_commit(id).map_err(to_io_error)?;
+                let signature = match &mailmap {
+                    Some(mailmap) => commit.author_with_mailmap(mailmap),
+                    None => Ok(commit.author().to_owned()),
+                }
+                .map_err(to_io_error)?;
+
+                let author = format!(
+                    "{} <{}>",
+                    String::from_utf8_lossy(signature.name_bytes()),
+                    String::from_utf8_lossy(signature.email_bytes())
+                );
+                names.insert(id, author.clone());
+                author
+            }
+        };
+
+        *authors.entry(author).or_default() += hunk.lines_in_hunk();
+    }
+
+    Ok(authors)
+}
+
+impl fmt::Display for Authorship {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let share = |lines: usize| match self.total {
+            0 => 0.0,
+            total => lines as f64 * 100.0 / total as f64,
+        };
+
+        // The most lines first
+        let mut authors = self.authors.iter().collect::<Vec<_>>();
+        authors.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        writeln!(f, "{:<50} {:>8} {:>7}", "Author", "Lines", "Share")?;
+
+        for (author, lines) in authors {
+            writeln!(f, "{:<50} {:>8} {:>6.1}%", author, lines, share(*lines))?;
+        }
+
+        writeln!(
+            f,
+            "{:<50} {:>8} {:>6.1}%",
+            "Total",
+            self.total,
+            share(self.total)
+        )?;
+        writeln!(f)?;
+        writeln!(f, "{:<16} {:<50} {:>8}", "Language", "Author", "Lines")?;
+
+        for (language, authors) in &self.languages {
+            let mut authors = authors.iter().collect::<Vec<_>>();
+            authors.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+            for (author, lines) in authors {
+                writeln!(f, "{:<16} {:<50} {:>8}", language, author, lines)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod blame_tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    use super::{blame, Authorship, UNCOMMITTED};
+    use crate::git::git_tests::commit;
+
+    #[test]
+    fn blame_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("main.rs");
+        commit(dir.path(), &[("main.rs", "a\nb\n")], "init");
+        fs::write(&main, "a\nb\nc\n").unwrap();
+
+        let authors = blame(&main, b"a\nb\nc\n", "a\nb\nc\n", false).unwrap();
+        assert_eq!(authors["Ada <ada@example.com>"], 2);
+        assert_eq!(authors[UNCOMMITTED], 1);
+
+        let scratch = dir.path().join("scratch.rs");
+        fs::write(&scratch, "x\n").unwrap();
+        let authors = blame(&scratch, b"x\n", "x\n", false).unwrap();
+        assert_eq!(authors[UNCOMMITTED], 1);
+    }
+
+    #[test]
+    fn blame_split_hunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("main.rs");
+        commit(dir.path(), &[("main.rs", "a\nb\nc\n")], "init");
+
+        // Changing a line in the middle splits the committed hunk
+        let authors = blame(&main, b"a\nx\nc\n", "a\nx\nc\n", false).unwrap();
+        assert_eq!(authors["Ada <ada@example.com>"], 2);
+        assert_eq!(authors[UNCOMMITTED], 1);
+    }
+
+    #[test]
+    fn blame_encodings() {
+        let dir = tempfile::tempdir().unwrap();
+        let latin = dir.path().join("latin.txt");
+        commit(dir.path(), &[("latin.txt", &b"caf\xe9\n"[..])], "init");
+
+        let authors = blame(&latin, b"caf\xe9\n", "caf\u{e9}\n", false).unwrap();
+        assert_eq!(authors["Ada <ada@example.com>"], 1);
+        assert!(!authors.contains_key(UNCOMMITTED));
+    }
+
+    #[test]
+    fn blame_unborn() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        let main = dir.path().join("main.rs");
+        fs::write(&main, "a\n").unwrap();
+
+        let authors = blame(&main, b"a\n", "a\n", false).unwrap();
+        assert_eq!(authors[UNCOMMITTED], 1);
+    }
+
+    #[test]
+    fn blame_mailmap() {
+        let dir = tempfile::tempdir().unwrap();
+        commit(
+            dir.path(),
+            &[
+                ("main.rs", "a\n"),
+                (
+                    ".mailmap",
+                    "Ada Lovelace <ada@example.org> <ada@example.com>\n",
+                ),
+            ],
+            "init",
+        );
+
+        let main = dir.path().join("main.rs");
+        let authors = blame(&main, b"a\n", "a\n", true).unwrap();
+        assert_eq!(authors["Ada Lovelace <ada@example.org>"], 1);
+
+        let authors = blame(&main, b"a\n", "a\n", false).unwrap();
+        assert_eq!(authors["Ada <ada@example.com>"], 1);
+    }
+
+    #[test]
+    fn blame_authorship_merge() {
+        let mut first = Authorship::default();
+        first.insert("Rust", BTreeMap::from([("Ada".to_string(), 3)]));
+
+        let mut second = Authorship::default();
+        second.insert("Rust", BTreeMap::from([("Ada".to_string(), 1)]));
+        second.insert("Python", BTreeMap::from([("Bob".to_string(), 2)]));
+
+        first.merge(second);
+        assert_eq!(first.total, 6);
+        assert_eq!(first.authors["Ada"], 4);
+        assert_eq!(first.languages["Rust"]["Ada"], 4);
+        assert_eq!(first.languages["Python"]["Bob"], 2);
+        assert!(first.to_string().starts_with("Author"));
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::{BufReader, Read, Result};
+use std::path::Path;
+
+/// A compression format xloc can read through.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Detects the compression format of a file from its extension.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "gz" => Some(Self::Gzip),
+            "bz2" => Some(Self::Bzip2),
+            "xz" => Some(Self::Xz),
+            "zst" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Wraps a reader so it yields the decompressed contents.
+    pub fn decoder<'a, R: Read + 'a>(self, reader: R) -> Result<Box<dyn Read + 'a>> {
+        let reader = BufReader::new(reader);
+
+        Ok(match self {
+            Self::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+            Self::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(reader)),
+            Self::Xz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader)),
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+        })
+    }
+}
+
+/// Strips a compression extension from a path, so `access.log.gz`
+/// becomes `access.log`. Other paths are returned as they are.
+pub fn inner_path(path: &Path) -> &Path {
+    match (Compression::detect(path), path.file_stem()) {
+        (Some(_), Some(stem)) => Path::new(stem),
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod compression_tests {
+    use std::fs::File;
+    use std::io::Read;
+    use std::path::Path;
+
+    use super::{inner_path, Compression};
+
+    #[test]
+    fn compression_detect() {
+        assert_eq!(
+            Compression::detect(Path::new("a.log.gz")),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::detect(Path::new("a.tar.ZST")),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::detect(Path::new("a.rs")), None);
+        assert_eq!(Compression::detect(Path::new("gz")), None);
+    }
+
+    #[test]
+    fn compression_inner_path() {
+        assert_eq!(
+            inner_path(Path::new("logs/access.log.gz")),
+            Path::new("access.log")
+        );
+        assert_eq!(
+            inner_path(Path::new("src/main.rs")),
+            Path::new("src/main.rs")
+        );
+    }
+
+    #[test]
+    fn compression_decoder() {
+        for ext in ["gz", "bz2", "xz", "zst"] {
+            let path = format!("tests/compressed/access.log.{}", ext);
+            let path = Path::new(&path);
+            let mut text = String::new();
+
+            Compression::detect(path)
+                .unwrap()
+                .decoder(File::open(path).unwrap())
+                .unwrap()
+                .read_to_string(&mut text)
+                .unwrap();
+
+            assert_eq!(text.lines().count(), 5, "{}", ext);
+        }
+    }
+}
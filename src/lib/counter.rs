@@ -1,10 +1,106 @@
 use std::fs;
 use std::io::Result;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use super::archive::{Archive, EntryRules};
+use super::git::{self, Snapshot};
+use super::ignores::{self, Globs, Ignores};
+use super::source::Source;
+use super::summary::{SkipReason, Skipped};
+use super::symlinks::{self, FileId, SymlinkPolicy};
+
+/// Options that control which files a [Counter] finds.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Whether to respect `.gitignore`, `.ignore` and `.xlocignore`
+    /// files, and the repository and global git excludes.
+    pub ignore: bool,
+
+    /// Globs a file must match one of to be counted, see [Globs].
+    pub include: Vec<String>,
+
+    /// Globs for files and directories to leave out, see [Globs].
+    pub exclude: Vec<String>,
+
+    /// Names of files and directories to leave out wherever they are,
+    /// the [DEFAULT_EXCLUDES](crate::DEFAULT_EXCLUDES) unless overridden.
+    pub default_excludes: Vec<String>,
+
+    /// Which symlinks found while scanning are followed.
+    pub symlinks: SymlinkPolicy,
+
+    /// Whether to count hidden files and search hidden directories,
+    /// whose names start with a `.`.
+    pub hidden: bool,
+
+    /// How many directories deep to search below the root, or no limit.
+    pub max_depth: Option<usize>,
+
+    /// Whether to stay on the filesystem the root is on, rather than
+    /// searching mount points below it.
+    pub one_file_system: bool,
+
+    /// Whether archives are expanded, so include globs don't leave them
+    /// out, like directories.
+    pub archives: bool,
+
+    /// Only count files last modified after this time, if set.
+    pub newer_than: Option<SystemTime>,
+
+    /// Only count files last modified before this time, if set.
+    pub older_than: Option<SystemTime>,
+}
+
+/// The `statfs` magic numbers of pseudo filesystems, which are never
+/// searched when found below the root wherever they are mounted, since
+/// their files describe the running system rather than hold data, and
+/// some of them never end.
+#[cfg(target_os = "linux")]
+pub const PSEUDO_FILESYSTEMS: [u32; 9] = [
+    0x9fa0,     // proc
+    0x62656572, // sysfs
+    0x64626720, // debugfs
+    0x74726163, // tracefs
+    0x73636673, // securityfs
+    0x27e0eb,   // cgroup
+    0x63677270, // cgroup2
+    0x6165676c, // pstore
+    0xcafe4a11, // bpf
+];
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            ignore: true,
+            include: vec![],
+            exclude: vec![],
+            default_excludes: ignores::default_excludes(),
+            symlinks: SymlinkPolicy::default(),
+            hidden: false,
+            max_depth: None,
+            one_file_system: false,
+            archives: false,
+            newer_than: None,
+            older_than: None,
+        }
+    }
+}
 
 pub struct Counter {
     pub path: PathBuf,
     pub files: Vec<PathBuf>,
+    pub skipped: Vec<Skipped>,
+    pub options: ScanOptions,
+    pub source: Source,
+    /// The rules for the entries of archives among the files, which are
+    /// the ones the files were found with.
+    pub rules: EntryRules,
+    globs: Globs,
+    root: Option<PathBuf>,
+    ancestors: Vec<FileId>,
+    device: Option<u64>,
 }
 
 impl Counter {
@@ -12,40 +108,177 @@ impl Counter {
         Self {
             path,
             files: vec![],
+            skipped: vec![],
+            options: ScanOptions::default(),
+            source: Source::default(),
+            rules: EntryRules::default(),
+            globs: Globs::default(),
+            root: None,
+            ancestors: vec![],
+            device: None,
         }
     }
 
-    fn scan(&mut self, path: &Path) -> Result<()> {
+    /// Creates a `Counter` for a list of files that is already known,
+    /// so they don't need to be scanned for.
+    pub fn with_files(files: Vec<PathBuf>) -> Self {
+        Self {
+            files,
+            ..Self::new(PathBuf::new())
+        }
+    }
+
+    fn scan(&mut self, path: &Path, ignores: &Ignores, depth: usize) -> Result<()> {
         if path.is_file() {
-            self.files.push(path.to_owned());
+            if self.is_modified_in_range(path) {
+                self.files.push(path.to_owned());
+            }
+
             return Ok(());
         }
 
-        for entry in fs::read_dir(path)?.filter(|d| {
-            let mut excluded = false;
+        if self.options.max_depth.is_some_and(|max| depth >= max) {
+            return Ok(());
+        }
 
-            for exclude in ["target", ".git"] {
-                excluded = !d.as_ref().unwrap().path().ends_with(exclude);
+        // Only the root is required to be readable, anything below it
+        // that can't be read is recorded and skipped
+        let listed = FileId::of(path).and_then(|id| Ok((id, fs::read_dir(path)?)));
+        let (id, entries) = match listed {
+            Ok(listed) => listed,
+            Err(e) if path != self.path => {
+                let reason = SkipReason::from(&e);
+                self.skipped.push(Skipped::new(path.to_owned(), reason));
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
 
-                if excluded {
-                    break;
+        // A directory that is already being scanned further up was
+        // reached again through a symlink, so scanning it would recurse
+        // forever
+        if self.ancestors.contains(&id) {
+            let reason = SkipReason::SymlinkLoop;
+            self.skipped.push(Skipped::new(path.to_owned(), reason));
+            return Ok(());
+        }
+
+        self.ancestors.push(id);
+
+        let ignores = match self.options.ignore {
+            true => ignores.descend(path),
+            false => Ignores::default(),
+        };
+
+        for entry in entries {
+            // An entry that fails to be read means the directory itself
+            // can't be listed fully, so it is recorded as skipped
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    let reason = SkipReason::from(&e);
+                    self.skipped.push(Skipped::new(path.to_owned(), reason));
+                    continue;
                 }
+            };
+
+            let is_dir = path.is_dir();
+
+            if !self.is_included(&path, is_dir, &ignores) || !self.follows(&path) {
+                continue;
             }
 
-            excluded
-        }) {
-            let path = entry?.path();
+            if is_dir && self.is_other_filesystem(&path) {
+                continue;
+            }
 
-            if path.is_dir() {
-                self.scan(&path)?;
-            } else {
+            if is_dir {
+                self.scan(&path, &ignores, depth + 1)?;
+            } else if self.is_modified_in_range(&path) {
                 self.files.push(path);
             }
         }
 
+        self.ancestors.pop();
         Ok(())
     }
 
+    /// Whether the directory at `path` is a pseudo filesystem, or on
+    /// another filesystem than the root when staying on one.
+    fn is_other_filesystem(&self, path: &Path) -> bool {
+        if is_pseudo_filesystem(path) {
+            return true;
+        }
+
+        self.options.one_file_system && symlinks::device_of(path) != self.device
+    }
+
+    /// Whether to scan `path` under the [SymlinkPolicy], if it is a
+    /// symlink. Broken symlinks are recorded and skipped.
+    fn follows(&mut self, path: &Path) -> bool {
+        if !symlinks::is_symlink(path) {
+            return true;
+        }
+
+        if fs::metadata(path).is_err() {
+            let reason = SkipReason::BrokenLink;
+            self.skipped.push(Skipped::new(path.to_owned(), reason));
+            return false;
+        }
+
+        match (self.options.symlinks, &self.root) {
+            (SymlinkPolicy::Never, _) => false,
+            (SymlinkPolicy::WithinRoot, Some(root)) => symlinks::is_within(path, root),
+            (SymlinkPolicy::WithinRoot, None) => false,
+            (SymlinkPolicy::Always, _) => true,
+        }
+    }
+
+    /// Whether a path found while scanning should be counted, or
+    /// searched if it is a directory.
+    fn is_included(&self, path: &Path, is_dir: bool, ignores: &Ignores) -> bool {
+        (self.options.hidden || !ignores::is_hidden(path))
+            && !ignores::is_excluded_name(path, &self.options.default_excludes)
+            && !ignores.is_ignored(path, is_dir)
+            && !self
+                .globs
+                .is_excluded(path, is_dir || self.is_archive(path))
+    }
+
+    /// Whether `path` is an archive that is expanded like a directory.
+    fn is_archive(&self, path: &Path) -> bool {
+        self.options.archives && Archive::detect(path).is_some()
+    }
+
+    /// The [EntryRules] for globs only, when nothing else applies.
+    fn glob_rules(&self) -> EntryRules {
+        EntryRules {
+            root: self.path.clone(),
+            include: self.options.include.clone(),
+            exclude: self.options.exclude.clone(),
+            ..EntryRules::default()
+        }
+    }
+
+    /// Whether a file was last modified between the times in the options.
+    /// Files whose modification time can't be read are kept, so the
+    /// reason they can't be read is reported when they are counted.
+    fn is_modified_in_range(&self, path: &Path) -> bool {
+        let (newer_than, older_than) = (self.options.newer_than, self.options.older_than);
+
+        if newer_than.is_none() && older_than.is_none() {
+            return true;
+        }
+
+        match fs::metadata(path).and_then(|m| m.modified()) {
+            Ok(modified) => {
+                newer_than.is_none_or(|time| modified > time)
+                    && older_than.is_none_or(|time| modified < time)
+            }
+            Err(_) => true,
+        }
+    }
+
     pub fn generate_workloads(&self, njobs: usize, nfiles: usize) -> Result<Vec<usize>> {
         let chunk_size = nfiles / njobs;
         let remainder = nfiles % njobs;
@@ -58,17 +291,115 @@ impl Counter {
         Ok(workloads)
     }
 
+    /// Finds the files tracked by git in the path's repository, instead
+    /// of scanning for them. Only the include and exclude globs and the
+    /// modification times apply, as the index already leaves out
+    /// untracked and ignored files.
+    pub fn count_tracked(&mut self) -> Result<usize> {
+        self.globs = Globs::new(&self.path, &self.options.include, &self.options.exclude)?;
+
+        let files = git::tracked_files(&self.path)?;
+
+        self.files = files
+            .into_iter()
+            .filter(|file| !self.globs.is_excluded(file, self.is_archive(file)))
+            .filter(|file| self.is_modified_in_range(file))
+            .collect();
+
+        self.rules = self.glob_rules();
+
+        Ok(self.files.len())
+    }
+
+    /// Finds the files in the path's tree at a git revision, which are
+    /// read from the repository rather than the working tree. Only the
+    /// include and exclude globs apply, as blobs have no modification
+    /// times.
+    pub fn count_revision(&mut self, rev: &str) -> Result<usize> {
+        self.globs = Globs::new(&self.path, &self.options.include, &self.options.exclude)?;
+
+        let snapshot = Snapshot::new(&self.path, rev)?;
+        self.files = snapshot
+            .files()
+            .into_iter()
+            .filter(|file| !self.globs.is_excluded(file, self.is_archive(file)))
+            .collect();
+
+        self.rules = self.glob_rules();
+
+        self.source = Source::Revision(Arc::new(snapshot));
+        Ok(self.files.len())
+    }
+
     pub fn count_files(&mut self) -> Result<usize> {
-        self.scan(&self.path.clone())?;
+        self.globs = Globs::new(&self.path, &self.options.include, &self.options.exclude)?;
+        self.root = fs::canonicalize(&self.path).ok();
+        self.ancestors.clear();
+        self.device = symlinks::device_of(&self.path);
+
+        let ignores = match self.options.ignore {
+            true => Ignores::root(&self.path),
+            false => Ignores::default(),
+        };
+
+        self.scan(&self.path.clone(), &ignores, 0)?;
+
+        self.rules = EntryRules {
+            default_excludes: self.options.default_excludes.clone(),
+            ignore: self.options.ignore,
+            skip_hidden: !self.options.hidden,
+            max_depth: self.options.max_depth,
+            ..self.glob_rules()
+        };
+
         Ok(self.files.len())
     }
 }
 
+/// Whether the directory at `path` is on one of the [PSEUDO_FILESYSTEMS],
+/// following symlinks.
+#[cfg(target_os = "linux")]
+fn is_pseudo_filesystem(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+
+    let mut stat = MaybeUninit::<libc::statfs>::uninit();
+
+    // SAFETY: `path` is NUL terminated, and `stat` is only read once
+    // `statfs` has filled it in
+    let f_type = match unsafe { libc::statfs(path.as_ptr(), stat.as_mut_ptr()) } {
+        0 => unsafe { stat.assume_init() }.f_type,
+        _ => return false,
+    };
+
+    // Magic numbers fit in 32 bits, though `f_type` is wider on some
+    // platforms and signed on others
+    PSEUDO_FILESYSTEMS.contains(&(f_type as u32))
+}
+
+/// Pseudo filesystems are only recognized on Linux.
+#[cfg(not(target_os = "linux"))]
+fn is_pseudo_filesystem(_path: &Path) -> bool {
+    false
+}
+
 #[cfg(test)]
 mod counter_tests {
+    use std::time::{Duration, SystemTime};
     use std::{path::PathBuf, str::FromStr};
 
+    use std::fs;
+
     use super::Counter;
+    use crate::ignores::{Ignores, DEFAULT_EXCLUDES};
+    use crate::summary::SkipReason;
+    use crate::symlinks::SymlinkPolicy;
 
     #[test]
     fn counter_new() {
@@ -79,12 +410,21 @@ mod counter_tests {
         assert_eq!(counter.files.len(), 0);
     }
 
+    #[test]
+    fn counter_with_files() {
+        let files = vec![PathBuf::from("a.rs"), PathBuf::from("b.rs")];
+        let counter = Counter::with_files(files.clone());
+
+        assert_eq!(counter.files, files);
+        assert!(counter.skipped.is_empty());
+    }
+
     #[test]
     fn counter_scan_dir() {
         let path = PathBuf::from_str("tests/data").unwrap();
         let mut counter = Counter::new(path.clone());
 
-        let result = counter.scan(&path);
+        let result = counter.scan(&path, &Ignores::default(), 0);
         assert!(result.is_ok());
     }
 
@@ -93,7 +433,7 @@ mod counter_tests {
         let path = PathBuf::from_str("tests/data/data.rs").unwrap();
         let mut counter = Counter::new(path.clone());
 
-        let result = counter.scan(&path);
+        let result = counter.scan(&path, &Ignores::default(), 0);
         assert!(result.is_ok());
     }
 
@@ -117,6 +457,221 @@ mod counter_tests {
         assert_eq!(result.unwrap(), 1);
     }
 
+    #[test]
+    fn counter_count_files_ignored() {
+        let path = PathBuf::from_str("tests/ignore").unwrap();
+        let mut counter = Counter::new(path.clone());
+
+        // main.rs, keep.log and nested/mod.rs
+        assert_eq!(counter.count_files().unwrap(), 3);
+
+        let mut counter = Counter::new(path.clone());
+        counter.options.ignore = false;
+        assert_eq!(counter.count_files().unwrap(), 7);
+
+        // The 4 ignore files are hidden
+        let mut counter = Counter::new(path);
+        counter.options.hidden = true;
+        assert_eq!(counter.count_files().unwrap(), 7);
+    }
+
+    #[test]
+    fn counter_count_files_globs() {
+        let path = PathBuf::from_str("tests/ignore").unwrap();
+        let mut counter = Counter::new(path.clone());
+        counter.options.ignore = false;
+        counter.options.include = vec!["*.rs".to_string()];
+        counter.options.exclude = vec!["nested/*".to_string(), "!mod.rs".to_string()];
+
+        // main.rs, build/out.rs, vendor/lib.rs and nested/mod.rs
+        assert_eq!(counter.count_files().unwrap(), 4);
+    }
+
+    #[test]
+    fn counter_count_files_default_excludes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+
+        for name in DEFAULT_EXCLUDES {
+            fs::create_dir(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join("lib.rs"), "\n").unwrap();
+        }
+
+        let mut counter = Counter::new(dir.path().to_owned());
+        counter.options.hidden = true;
+        assert_eq!(counter.count_files().unwrap(), 1);
+
+        // Only target is excluded, so the rest are counted
+        let mut counter = Counter::new(dir.path().to_owned());
+        counter.options.hidden = true;
+        counter.options.default_excludes = vec!["target".to_string()];
+        assert_eq!(counter.count_files().unwrap(), DEFAULT_EXCLUDES.len());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn counter_count_files_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        let other = dir.path().join("other");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(root.join("a.rs"), "\n").unwrap();
+        fs::write(root.join("sub/b.rs"), "\n").unwrap();
+        fs::write(other.join("c.rs"), "\n").unwrap();
+        symlink(&root, root.join("sub/loop")).unwrap();
+        symlink(root.join("sub/b.rs"), root.join("link.rs")).unwrap();
+        symlink(&other, root.join("other")).unwrap();
+        symlink(root.join("missing.rs"), root.join("broken.rs")).unwrap();
+
+        let count = |policy| {
+            let mut counter = Counter::new(root.clone());
+            counter.options.symlinks = policy;
+            counter.count_files().unwrap();
+            counter
+        };
+
+        let counter = count(SymlinkPolicy::Never);
+        assert_eq!(counter.files.len(), 2);
+        assert_eq!(counter.skipped.len(), 1);
+        assert_eq!(counter.skipped[0].reason, SkipReason::BrokenLink);
+
+        // The loop back to the root is detected, other/ is outside it
+        let counter = count(SymlinkPolicy::WithinRoot);
+        assert_eq!(counter.files.len(), 3);
+        assert_eq!(counter.skipped.len(), 2);
+        assert!(counter
+            .skipped
+            .iter()
+            .any(|s| s.reason == SkipReason::SymlinkLoop && s.path.ends_with("sub/loop")));
+
+        let counter = count(SymlinkPolicy::Always);
+        assert_eq!(counter.files.len(), 4);
+        assert!(counter.files.contains(&root.join("other/c.rs")));
+    }
+
+    #[test]
+    fn counter_count_files_max_depth() {
+        let path = PathBuf::from_str("tests/ignore").unwrap();
+        let count = |max_depth| {
+            let mut counter = Counter::new(path.clone());
+            counter.options.ignore = false;
+            counter.options.max_depth = max_depth;
+            counter.count_files().unwrap()
+        };
+
+        assert_eq!(count(Some(0)), 0);
+        assert_eq!(count(Some(1)), 3);
+        assert_eq!(count(Some(2)), 7);
+        assert_eq!(count(None), 7);
+    }
+
+    #[test]
+    fn counter_count_files_repo_exclude() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join(".git/info/exclude"), "secret.rs\n").unwrap();
+        fs::write(dir.path().join("main.rs"), "\n").unwrap();
+        fs::write(dir.path().join("secret.rs"), "\n").unwrap();
+
+        let mut counter = Counter::new(dir.path().to_owned());
+        assert_eq!(counter.count_files().unwrap(), 1);
+        assert!(counter.files[0].ends_with("main.rs"));
+    }
+
+    #[test]
+    fn counter_count_files_modified() {
+        let dir = tempfile::tempdir().unwrap();
+        let day = Duration::from_secs(86_400);
+        let now = SystemTime::now();
+
+        for (name, age) in [("old.rs", 60), ("recent.rs", 10), ("new.rs", 0)] {
+            let file = fs::File::create(dir.path().join(name)).unwrap();
+            file.set_modified(now - day * age).unwrap();
+        }
+
+        let count = |newer_than, older_than| {
+            let mut counter = Counter::new(dir.path().to_owned());
+            counter.options.newer_than = newer_than;
+            counter.options.older_than = older_than;
+            counter.count_files().unwrap()
+        };
+
+        assert_eq!(count(None, None), 3);
+        assert_eq!(count(Some(now - day * 30), None), 2);
+        assert_eq!(count(None, Some(now - day * 30)), 1);
+        assert_eq!(count(Some(now - day * 30), Some(now - day)), 1);
+
+        // The root is filtered too when it is a file
+        let mut counter = Counter::new(dir.path().join("old.rs"));
+        counter.options.newer_than = Some(now - day * 30);
+        assert_eq!(counter.count_files().unwrap(), 0);
+    }
+
+    #[test]
+    fn counter_count_files_one_file_system() {
+        let path = PathBuf::from_str("tests/data").unwrap();
+        let mut counter = Counter::new(path);
+        counter.options.one_file_system = true;
+
+        // Everything is on the same filesystem as the root
+        assert_eq!(counter.count_files().unwrap(), 3);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn counter_count_files_other_file_system() {
+        use std::os::unix::fs::symlink;
+
+        use crate::symlinks::device_of;
+
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("a.rs"), "\n").unwrap();
+
+        // A directory on another device, reached through a symlink
+        let other = match tempfile::tempdir_in("/dev/shm") {
+            Ok(other) if device_of(other.path()) != device_of(root.path()) => other,
+            _ => return,
+        };
+        fs::write(other.path().join("b.rs"), "\n").unwrap();
+        symlink(other.path(), root.path().join("mnt")).unwrap();
+
+        let count = |one_file_system| {
+            let mut counter = Counter::new(root.path().to_owned());
+            counter.options.symlinks = SymlinkPolicy::Always;
+            counter.options.one_file_system = one_file_system;
+            counter.count_files().unwrap()
+        };
+
+        assert_eq!(count(false), 2);
+        assert_eq!(count(true), 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn counter_count_files_pseudo_filesystem() {
+        use std::os::unix::fs::symlink;
+        use std::path::Path;
+
+        use super::is_pseudo_filesystem;
+
+        assert!(is_pseudo_filesystem(Path::new("/proc")));
+        assert!(is_pseudo_filesystem(Path::new("/proc/self")));
+        assert!(!is_pseudo_filesystem(Path::new("/dev")));
+        assert!(!is_pseudo_filesystem(Path::new("tests/data")));
+
+        // Wherever it is mounted, or linked to, proc is never searched
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("a.rs"), "\n").unwrap();
+        symlink("/proc", root.path().join("proc")).unwrap();
+
+        let mut counter = Counter::new(root.path().to_owned());
+        counter.options.symlinks = SymlinkPolicy::Always;
+        assert_eq!(counter.count_files().unwrap(), 1);
+    }
+
     #[test]
     fn counter_generate_workloads() {
         let path = PathBuf::from_str("tests/data").unwrap();
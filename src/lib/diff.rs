@@ -0,0 +1,520 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use git2::{DiffFile, DiffOptions, FileMode, Oid, Patch, Repository};
+
+use super::archive::EntryRules;
+use super::git::{self, to_io_error};
+use super::language::{self, LineKind};
+use super::source::Source;
+use super::summary::{SkipReason, Skipped};
+
+/// Which uncommitted changes of a repository to compare.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Changes {
+    /// What is staged in the index, compared to `HEAD`.
+    Staged,
+
+    /// What is in the working tree, compared to the index.
+    Unstaged,
+}
+
+/// The lines of one kind added and removed between two versions.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Change {
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl Change {
+    /// The lines added less the lines removed.
+    pub fn net(&self) -> isize {
+        self.added as isize - self.removed as isize
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.added += other.added;
+        self.removed += other.removed;
+    }
+}
+
+/// The code, comment and blank lines added and removed in a file, or a
+/// group of files.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Delta {
+    /// The number of changed files these deltas cover.
+    pub files: usize,
+
+    pub code: Change,
+    pub comments: Change,
+    pub blanks: Change,
+}
+
+impl Delta {
+    /// Compares two versions of a file, either of which may be empty if
+    /// the file was added or removed. The `path` picks the language, to
+    /// tell comments from code.
+    pub fn between(path: &Path, old: &str, new: &str) -> Result<Self> {
+        if old == new {
+            return Ok(Self::default());
+        }
+
+        let patch = Patch::from_buffers(old.as_bytes(), None, new.as_bytes(), None, None)
+            .map_err(to_io_error)?;
+
+        Self::from_patch(path, &patch, old, new)
+    }
+
+    /// Counts the lines a patch adds and removes, telling comments from
+    /// code by the two versions of the file it was made from. A line
+    /// past the end of its version, which a filter that changes the
+    /// number of lines could cause, counts as blank.
+    fn from_patch(path: &Path, patch: &Patch, old: &str, new: &str) -> Result<Self> {
+        let mut delta = Self::default();
+        let old_kinds = language::classify(path, old);
+        let new_kinds = language::classify(path, new);
+
+        for hunk in 0..patch.num_hunks() {
+            let nlines = patch.num_lines_in_hunk(hunk).map_err(to_io_error)?;
+
+            for i in 0..nlines {
+                let line = patch.line_in_hunk(hunk, i).map_err(to_io_error)?;
+
+                let kind = |kinds: &[LineKind], n: Option<u32>| {
+                    n.and_then(|n| kinds.get(n as usize - 1).copied())
+                };
+
+                let (kind, added) = match line.origin() {
+                    '+' => (kind(&new_kinds, line.new_lineno()), true),
+                    '-' => (kind(&old_kinds, line.old_lineno()), false),
+                    _ => continue,
+                };
+
+                let change = match kind.unwrap_or(LineKind::Blank) {
+                    LineKind::Code => &mut delta.code,
+                    LineKind::Comment => &mut delta.comments,
+                    LineKind::Blank => &mut delta.blanks,
+                };
+
+                match added {
+                    true => change.added += 1,
+                    false => change.removed += 1,
+                }
+            }
+        }
+
+        delta.files = usize::from(patch.num_hunks() > 0);
+        Ok(delta)
+    }
+
+    /// Adds another delta into this one.
+    pub fn add(&mut self, other: &Self) {
+        self.files += other.files;
+        self.code.add(&other.code);
+        self.comments.add(&other.comments);
+        self.blanks.add(&other.blanks);
+    }
+
+    /// Whether no lines were added or removed.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// The lines added and removed between two trees, broken down per file
+/// and per language.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct DiffReport {
+    /// Deltas for each changed file, named relative to its tree.
+    pub files: BTreeMap<PathBuf, Delta>,
+
+    /// Deltas for each language, keyed by language name.
+    pub languages: BTreeMap<String, Delta>,
+
+    /// Deltas for every file combined.
+    pub total: Delta,
+
+    /// Files on either side that couldn't be compared, and why.
+    pub skipped: Vec<Skipped>,
+}
+
+impl DiffReport {
+    /// Records the delta for a single file, unless nothing changed.
+    pub fn insert(&mut self, file: PathBuf, delta: Delta) {
+        if delta.is_empty() {
+            return;
+        }
+
+        self.languages
+            .entry(language::name_of(&file).to_string())
+            .or_default()
+            .add(&delta);
+
+        self.total.add(&delta);
+        self.files.insert(file, delta);
+    }
+}
+
+/// One side of a diff between two trees.
+#[derive(Debug, Clone, Default)]
+pub struct Side {
+    /// The directory, or the path in a revision, the side was found in.
+    pub root: PathBuf,
+
+    /// Where the side's files are read from.
+    pub source: Source,
+
+    /// The files of the side, keyed by their name within it.
+    pub files: BTreeMap<PathBuf, PathBuf>,
+
+    /// The rules for the entries of archives on the side.
+    pub rules: EntryRules,
+}
+
+impl Side {
+    /// Whether a file is the same blob on both sides, which are
+    /// revisions of one repository, so it is unchanged without reading
+    /// it.
+    pub fn is_same_blob(&self, other: &Self, name: &Path) -> bool {
+        match (self.blob_id(name), other.blob_id(name)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    fn blob_id(&self, name: &Path) -> Option<Oid> {
+        match &self.source {
+            Source::Revision(snapshot) => snapshot.id(self.files.get(name)?).ok(),
+            Source::Filesystem => None,
+        }
+    }
+}
+
+/// Reports the lines added and removed by uncommitted changes to
+/// `path`, in the repository containing it. Only files `keep` accepts
+/// are compared, after `decode` turns their contents into text, and
+/// files over `max_size` bytes on either side are skipped without
+/// reading them. Renames are compared with the file they were renamed
+/// from, while untracked files, symlinks and submodules are left out,
+/// like in `git diff`.
+pub fn uncommitted<K, D>(
+    path: &Path,
+    changes: Changes,
+    max_size: Option<u64>,
+    keep: K,
+    decode: D,
+) -> Result<DiffReport>
+where
+    K: Fn(&Path) -> bool,
+    D: Fn(&Path, &[u8]) -> std::result::Result<String, SkipReason>,
+{
+    let (repo, prefix) = git::discover(path)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Bare repositories have no changes"))?
+        .to_owned();
+
+    let mut options = DiffOptions::new();
+
+    if !prefix.as_os_str().is_empty() {
+        options.pathspec(&prefix);
+    }
+
+    let diff = match changes {
+        Changes::Staged => {
+            // Everything in the index is staged before the first commit
+            let head = repo.head().and_then(|head| head.peel_to_tree()).ok();
+            repo.diff_tree_to_index(head.as_ref(), None, Some(&mut options))
+        }
+        Changes::Unstaged => repo.diff_index_to_workdir(None, Some(&mut options)),
+    };
+
+    let mut diff = diff.map_err(to_io_error)?;
+    diff.find_similar(None).map_err(to_io_error)?;
+
+    let mut report = DiffReport::default();
+
+    for (i, delta) in diff.deltas().enumerate() {
+        let (old, new) = (delta.old_file(), delta.new_file());
+        let linked = [old.mode(), new.mode()]
+            .iter()
+            .any(|mode| matches!(mode, FileMode::Link | FileMode::Commit));
+
+        let inner = match new.path().or(old.path()) {
+            Some(inner) if !linked => inner.strip_prefix(&prefix).unwrap_or(inner),
+            _ => continue,
+        };
+
+        let file = path.join(inner);
+
+        if !keep(&file) {
+            continue;
+        }
+
+        // The working tree side of unstaged changes is only on disk
+        let on_disk = match changes {
+            Changes::Unstaged if new.exists() => Some(workdir.join(new.path().unwrap_or(inner))),
+            _ => None,
+        };
+
+        if let Some(max) = max_size {
+            let sizes = blob_size(&repo, &old).and_then(|old| {
+                let new = match &on_disk {
+                    Some(on_disk) => fs::metadata(on_disk)?.len(),
+                    None => blob_size(&repo, &new)?,
+                };
+
+                Ok(old.max(new))
+            });
+
+            let skip = match sizes {
+                Ok(size) => (size > max).then_some(SkipReason::TooLarge),
+                Err(e) => Some(SkipReason::from(&e)),
+            };
+
+            if let Some(reason) = skip {
+                report.skipped.push(Skipped::new(file, reason));
+                continue;
+            }
+        }
+
+        let new_bytes = match &on_disk {
+            Some(on_disk) => fs::read(on_disk),
+            None => read_blob(&repo, &new),
+        };
+
+        let texts = read_blob(&repo, &old)
+            .and_then(|old| Ok((old, new_bytes?)))
+            .map_err(|e| SkipReason::from(&e))
+            .and_then(|(old, new)| Ok((decode(&file, &old)?, decode(&file, &new)?)));
+
+        let (old, new) = match texts {
+            Ok(texts) => texts,
+            Err(reason) => {
+                report.skipped.push(Skipped::new(file, reason));
+                continue;
+            }
+        };
+
+        // The patch compares the sides as git does, after filters like
+        // line ending conversion, so only real changes are counted
+        let delta = match Patch::from_diff(&diff, i).map_err(to_io_error)? {
+            Some(patch) => Delta::from_patch(inner, &patch, &old, &new)?,
+            None => Delta::default(),
+        };
+
+        report.insert(inner.to_owned(), delta);
+    }
+
+    Ok(report)
+}
+
+/// Gets the size in bytes of one side of a change in the repository,
+/// from the blob's header only, which is 0 if the file doesn't exist on
+/// that side.
+fn blob_size(repo: &Repository, file: &DiffFile) -> Result<u64> {
+    match file.exists() {
+        true => {
+            let odb = repo.odb().map_err(to_io_error)?;
+            let (size, _) = odb.read_header(file.id()).map_err(to_io_error)?;
+            Ok(size as u64)
+        }
+        false => Ok(0),
+    }
+}
+
+/// Reads the contents of one side of a change from the repository, which
+/// are empty if the file doesn't exist on that side.
+fn read_blob(repo: &Repository, file: &DiffFile) -> Result<Vec<u8>> {
+    match file.exists() {
+        true => {
+            let blob = repo.find_blob(file.id()).map_err(to_io_error)?;
+            Ok(blob.content().to_vec())
+        }
+        false => Ok(vec![]),
+    }
+}
+
+fn write_header(f: &mut fmt::Formatter, title: &str) -> fmt::Result {
+    writeln!(
+        f,
+        "{:<40} {:>6} {:>8} {:>8} {:>8} {:>10} {:>10} {:>10} {:>8} {:>8} {:>8}",
+        title,
+        "Files",
+        "Code +",
+        "Code -",
+        "Code net",
+        "Comment +",
+        "Comment -",
+        "Comment net",
+        "Blank +",
+        "Blank -",
+        "Blank net"
+    )
+}
+
+fn write_row(f: &mut fmt::Formatter, name: &str, d: &Delta) -> fmt::Result {
+    writeln!(
+        f,
+        "{:<40} {:>6} {:>8} {:>8} {:>+8} {:>10} {:>10} {:>+10} {:>8} {:>8} {:>+8}",
+        name,
+        d.files,
+        d.code.added,
+        d.code.removed,
+        d.code.net(),
+        d.comments.added,
+        d.comments.removed,
+        d.comments.net(),
+        d.blanks.added,
+        d.blanks.removed,
+        d.blanks.net()
+    )
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_header(f, "File")?;
+
+        for (file, delta) in &self.files {
+            write_row(f, &file.display().to_string(), delta)?;
+        }
+
+        writeln!(f)?;
+        write_header(f, "Language")?;
+
+        for (language, delta) in &self.languages {
+            write_row(f, language, delta)?;
+        }
+
+        write_row(f, "Total", &self.total)
+    }
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use git2::Repository;
+
+    use super::{uncommitted, Changes, Delta, DiffReport};
+    use crate::git::git_tests::commit;
+    use crate::summary::SkipReason;
+
+    #[test]
+    fn diff_delta_between() {
+        let old = "// main\nfn main() {\n}\n";
+        let new = "// main\n\n/// Runs\nfn main() {\n    run();\n}\n";
+
+        let delta = Delta::between(Path::new("main.rs"), old, new).unwrap();
+        assert_eq!(delta.files, 1);
+        assert_eq!((delta.code.added, delta.code.removed), (1, 0));
+        assert_eq!(delta.comments.added, 1);
+        assert_eq!(delta.blanks.added, 1);
+
+        let delta = Delta::between(Path::new("main.rs"), old, "").unwrap();
+        assert_eq!(delta.code.net(), -2);
+        assert_eq!(delta.comments.net(), -1);
+
+        assert!(Delta::between(Path::new("main.rs"), old, old)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn diff_report_insert() {
+        let mut report = DiffReport::default();
+        let added = Delta::between(Path::new("a.rs"), "", "x\n").unwrap();
+        let removed = Delta::between(Path::new("b.py"), "# y\n", "").unwrap();
+
+        report.insert(PathBuf::from("a.rs"), added);
+        report.insert(PathBuf::from("b.py"), removed);
+        report.insert(PathBuf::from("c.rs"), Delta::default());
+
+        assert_eq!(report.files.len(), 2);
+        assert_eq!(report.languages["Rust"].code.net(), 1);
+        assert_eq!(report.languages["Python"].comments.net(), -1);
+        assert_eq!(report.total.files, 2);
+    }
+
+    #[test]
+    fn diff_uncommitted() {
+        let dir = tempfile::tempdir().unwrap();
+        commit(
+            dir.path(),
+            &[("src/main.rs", "fn main() {}\n"), ("old.py", "x = 1\n")],
+            "init",
+        );
+
+        // Stage a comment and a removal, then change the working tree again
+        fs::write(dir.path().join("src/main.rs"), "// hi\nfn main() {}\n").unwrap();
+        let repo = Repository::open(dir.path()).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("src/main.rs")).unwrap();
+        index.remove_path(Path::new("old.py")).unwrap();
+        index.write().unwrap();
+        fs::write(
+            dir.path().join("src/main.rs"),
+            "// hi\nfn main() {}\nfn run() {}\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("untracked.rs"), "\n").unwrap();
+
+        let decode = |_: &Path, bytes: &[u8]| Ok(String::from_utf8_lossy(bytes).into_owned());
+        let staged = uncommitted(dir.path(), Changes::Staged, None, |_| true, decode).unwrap();
+        assert_eq!(staged.files.len(), 2);
+        assert_eq!(staged.languages["Rust"].comments.added, 1);
+        assert_eq!(staged.languages["Python"].code.removed, 1);
+
+        let unstaged = uncommitted(dir.path(), Changes::Unstaged, None, |_| true, decode).unwrap();
+        assert_eq!(
+            unstaged.files.keys().collect::<Vec<_>>(),
+            [Path::new("src/main.rs")]
+        );
+        assert_eq!(unstaged.total.code.net(), 1);
+
+        let src = dir.path().join("src");
+        let staged = uncommitted(&src, Changes::Staged, None, |_| true, decode).unwrap();
+        assert_eq!(
+            staged.files.keys().collect::<Vec<_>>(),
+            [Path::new("main.rs")]
+        );
+
+        let keep = |file: &Path| file.extension().is_some_and(|e| e == "py");
+        let staged = uncommitted(dir.path(), Changes::Staged, None, keep, decode).unwrap();
+        assert_eq!(staged.files.len(), 1);
+
+        // Files over the limit on either side are skipped, not the others
+        let staged = uncommitted(dir.path(), Changes::Staged, Some(16), |_| true, decode).unwrap();
+        assert_eq!(staged.files.len(), 1);
+        assert_eq!(staged.skipped.len(), 1);
+        assert_eq!(staged.skipped[0].reason, SkipReason::TooLarge);
+    }
+
+    #[test]
+    fn diff_uncommitted_line_endings() {
+        let dir = tempfile::tempdir().unwrap();
+        commit(dir.path(), &[("main.rs", "fn a() {}\nfn b() {}\n")], "init");
+
+        let repo = Repository::open(dir.path()).unwrap();
+        repo.config()
+            .unwrap()
+            .set_bool("core.autocrlf", true)
+            .unwrap();
+
+        // Checked out with CRLF, as autocrlf would, and one line added
+        fs::write(
+            dir.path().join("main.rs"),
+            "fn a() {}\r\nfn b() {}\r\nfn c() {}\r\n",
+        )
+        .unwrap();
+
+        let decode = |_: &Path, bytes: &[u8]| Ok(String::from_utf8_lossy(bytes).into_owned());
+        let unstaged = uncommitted(dir.path(), Changes::Unstaged, None, |_| true, decode).unwrap();
+        assert_eq!(unstaged.total.code.added, 1);
+        assert_eq!(unstaged.total.code.removed, 0);
+    }
+}
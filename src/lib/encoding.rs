@@ -0,0 +1,92 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+/// How many leading bytes are inspected when looking for binary content.
+pub const SNIFF_LEN: usize = 8192;
+
+/// Files whose first block has more than this fraction of control
+/// characters are considered binary.
+const MAX_CONTROL_RATIO: f64 = 0.1;
+
+/// Sniffs the encoding of a file's contents from its byte order mark,
+/// returning the encoding and the length of the mark.
+///
+/// Files without a mark are assumed to be UTF-8 when they are valid
+/// UTF-8, and Windows-1252 (a superset of Latin-1) otherwise.
+pub fn sniff(bytes: &[u8]) -> (&'static Encoding, usize) {
+    if let Some(found) = Encoding::for_bom(bytes) {
+        return found;
+    }
+
+    // A sequence cut off at the end of the block is still valid UTF-8,
+    // the rest of it just hasn't been read yet
+    match std::str::from_utf8(bytes) {
+        Err(e) if e.error_len().is_some() => (WINDOWS_1252, 0),
+        _ => (encoding_rs::UTF_8, 0),
+    }
+}
+
+/// Whether newlines in this encoding are something other than a single
+/// `\n` byte, so lines can't be counted on the raw bytes.
+pub fn is_wide(encoding: &'static Encoding) -> bool {
+    encoding == UTF_16LE || encoding == UTF_16BE
+}
+
+/// Whether a file's contents look like binary data rather than text.
+///
+/// Only the first [SNIFF_LEN] bytes are inspected. Content is binary if
+/// it contains a NUL byte, or too many control characters other than
+/// whitespace and escape sequences. Text with a `UTF-16` byte order mark
+/// is never binary, even though it is full of NUL bytes.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let block = &bytes[..bytes.len().min(SNIFF_LEN)];
+
+    if block.is_empty() || is_wide(sniff(block).0) {
+        return false;
+    }
+
+    if block.contains(&0) {
+        return true;
+    }
+
+    let control = block
+        .iter()
+        .filter(|b| match b {
+            b'\t' | b'\n' | b'\r' | b'\x0C' | b'\x1B' => false,
+            _ => **b < 0x20 || **b == 0x7F,
+        })
+        .count();
+
+    control as f64 / block.len() as f64 > MAX_CONTROL_RATIO
+}
+
+#[cfg(test)]
+mod encoding_tests {
+    use encoding_rs::{UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+    use super::{is_binary, is_wide, sniff};
+
+    #[test]
+    fn encoding_sniff_bom() {
+        assert_eq!(sniff(b"\xEF\xBB\xBFhi"), (UTF_8, 3));
+        assert_eq!(sniff(b"\xFF\xFEh\0i\0"), (UTF_16LE, 2));
+        assert_eq!(sniff(b"\xFE\xFF\0h\0i"), (UTF_16BE, 2));
+    }
+
+    #[test]
+    fn encoding_sniff_no_bom() {
+        assert_eq!(sniff("caf\u{e9}".as_bytes()), (UTF_8, 0));
+        assert_eq!(sniff(b"caf\xE9 au lait"), (WINDOWS_1252, 0));
+        assert_eq!(sniff(b"caf\xC3"), (UTF_8, 0));
+        assert!(!is_wide(WINDOWS_1252));
+        assert!(is_wide(UTF_16BE));
+    }
+
+    #[test]
+    fn encoding_is_binary() {
+        assert!(is_binary(b"\x7FELF\x02\x01\x01\0\0\0"));
+        assert!(is_binary(b"\x01\x02\x03\x04 almost text"));
+        assert!(!is_binary(b"plain text\r\n\twith \x1B[1mcolour\x1B[0m\n"));
+        assert!(!is_binary(b"\xFF\xFEh\0i\0"));
+        assert!(!is_binary(b""));
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Component, Path, PathBuf};
+
+use git2::{Blob, ErrorCode, ObjectType, Oid, Repository, Tree, TreeWalkMode, TreeWalkResult};
+
+/// The file mode of a submodule entry in a git index or tree.
+const GITLINK_MODE: u32 = 0o160000;
+
+/// The file mode of a symlink entry in a git tree.
+pub const SYMLINK_MODE: i32 = 0o120000;
+
+/// The files of a path in one revision of a repository, which are read
+/// from its object database rather than the working tree.
+#[derive(Debug)]
+pub struct Snapshot {
+    git_dir: PathBuf,
+
+    /// The path in the tree and blob id of each file, by the name it is
+    /// counted as.
+    blobs: HashMap<PathBuf, (PathBuf, Oid)>,
+}
+
+impl Snapshot {
+    /// Lists the files of `path` in the tree of `rev`, which is a commit,
+    /// tag, branch or anything else `git rev-parse` accepts. Files are
+    /// named relative to `path` like [tracked_files] names them, while
+    /// symlinks and submodules are left out.
+    pub fn new(path: &Path, rev: &str) -> Result<Self> {
+        let (repo, prefix) = discover(path)?;
+        let tree = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_tree())
+            .map_err(to_io_error)?;
+
+        let mut blobs = HashMap::new();
+
+        if prefix.as_os_str().is_empty() {
+            collect_blobs(&tree, path, &prefix, &mut blobs)?;
+        } else {
+            let entry = tree.get_path(&prefix).map_err(|_| {
+                let message = format!("{} is not in {}", path.display(), rev);
+                Error::new(ErrorKind::NotFound, message)
+            })?;
+
+            match entry.kind() {
+                Some(ObjectType::Tree) => {
+                    let subtree = repo.find_tree(entry.id()).map_err(to_io_error)?;
+                    collect_blobs(&subtree, path, &prefix, &mut blobs)?;
+                }
+                Some(ObjectType::Blob) if entry.filemode() != SYMLINK_MODE => {
+                    blobs.insert(path.to_owned(), (prefix, entry.id()));
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            git_dir: repo.path().to_owned(),
+            blobs,
+        })
+    }
+
+    /// The files in the snapshot, sorted by name.
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = self.blobs.keys().cloned().collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    /// The path in the tree of a file in the snapshot, which is the same
+    /// whichever path argument it was found through.
+    pub fn tree_path(&self, file: &Path) -> Option<&Path> {
+        self.blobs.get(file).map(|(inner, _)| inner.as_path())
+    }
+
+    /// Opens the snapshot's repository. Each thread needs its own, as a
+    /// repository can't be shared between threads.
+    pub fn open(&self) -> Result<Repository> {
+        Repository::open(&self.git_dir).map_err(to_io_error)
+    }
+
+    /// Finds the blob of a file in the snapshot in `repo`. Its contents
+    /// are inflated whole by git, so [Snapshot::size] should be checked
+    /// first to leave out files that are too large.
+    pub fn blob<'r>(&self, repo: &'r Repository, file: &Path) -> Result<Blob<'r>> {
+        repo.find_blob(self.id(file)?).map_err(to_io_error)
+    }
+
+    /// Gets the size in bytes of a file in the snapshot from `repo`,
+    /// without reading its contents.
+    pub fn size(&self, repo: &Repository, file: &Path) -> Result<u64> {
+        let odb = repo.odb().map_err(to_io_error)?;
+        let (size, _) = odb.read_header(self.id(file)?).map_err(to_io_error)?;
+        Ok(size as u64)
+    }
+
+    /// Gets the id of the blob of a file in the snapshot.
+    pub fn id(&self, file: &Path) -> Result<Oid> {
+        let (_, id) = self.blobs.get(file).ok_or_else(|| {
+            let message = format!("{} is not in the revision", file.display());
+            Error::new(ErrorKind::NotFound, message)
+        })?;
+
+        Ok(*id)
+    }
+}
+
+/// Opens the repository containing `path`, along with the path of
+/// `path` within the repository's tree. In a bare repository, which has
+/// no working tree, that is the root of the tree.
+///
+/// `path` doesn't need to exist, so a directory deleted since a revision
+/// can still be found in its tree.
+pub fn discover(path: &Path) -> Result<(Repository, PathBuf)> {
+    let (existing, root) = resolve(path)?;
+    let repo = Repository::discover(existing).map_err(to_io_error)?;
+
+    let prefix = match repo.workdir() {
+        Some(workdir) => root
+            .strip_prefix(fs::canonicalize(workdir)?)
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+        None => PathBuf::new(),
+    };
+
+    Ok((repo, prefix))
+}
+
+/// Canonicalizes the nearest ancestor of `path` that exists, and joins
+/// the rest of `path` to it as written. Returns both the ancestor and
+/// the resolved path.
+fn resolve(path: &Path) -> Result<(PathBuf, PathBuf)> {
+    let path = std::path::absolute(path)?;
+    let mut missing = vec![];
+    let mut existing = path.as_path();
+
+    let existing = loop {
+        match fs::canonicalize(existing) {
+            Ok(root) => break root,
+            Err(e) if e.kind() == ErrorKind::NotFound => match existing.parent() {
+                Some(parent) => {
+                    missing.extend(existing.components().next_back());
+                    existing = parent;
+                }
+                None => return Err(e),
+            },
+            Err(e) => return Err(e),
+        }
+    };
+
+    let mut root = existing.clone();
+
+    for component in missing.into_iter().rev() {
+        match component {
+            Component::ParentDir => {
+                root.pop();
+            }
+            Component::Normal(name) => root.push(name),
+            _ => (),
+        }
+    }
+
+    Ok((existing, root))
+}
+
+/// Adds every file in `tree`, which is at `prefix` in the revision, to
+/// `blobs`, named below `path`.
+fn collect_blobs(
+    tree: &Tree,
+    path: &Path,
+    prefix: &Path,
+    blobs: &mut HashMap<PathBuf, (PathBuf, Oid)>,
+) -> Result<()> {
+    tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.kind() == Some(ObjectType::Blob) && entry.filemode() != SYMLINK_MODE {
+            let inner = Path::new(dir).join(path_from_bytes(entry.name_bytes()));
+            blobs.insert(path.join(&inner), (prefix.join(&inner), entry.id()));
+        }
+
+        TreeWalkResult::Ok
+    })
+    .map_err(to_io_error)
+}
+
+/// Lists the files tracked in the index of the repository containing
+/// `path`, that are `path` itself or inside it. Tracked files of checked
+/// out submodules are included, and worktrees read their own index.
+///
+/// The listed files are named relative to `path` the same way a scan of
+/// `path` would name them, so `src` lists `src/main.rs`.
+pub fn tracked_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let root = fs::canonicalize(path)?;
+    let repo = Repository::discover(&root).map_err(to_io_error)?;
+    let mut tracked = vec![];
+
+    collect_tracked(&repo, &mut tracked)?;
+
+    Ok(tracked
+        .into_iter()
+        .filter_map(|file| match file.strip_prefix(&root) {
+            Ok(inner) if inner.as_os_str().is_empty() => Some(path.to_owned()),
+            Ok(inner) => Some(path.join(inner)),
+            Err(_) => None,
+        })
+        .collect())
+}
+
+/// Adds the absolute path of every file in `repo`'s index to `tracked`,
+/// recursing into submodules that are checked out.
+fn collect_tracked(repo: &Repository, tracked: &mut Vec<PathBuf>) -> Result<()> {
+    let workdir = match repo.workdir() {
+        Some(workdir) => fs::canonicalize(workdir)?,
+        None => return Err(bare_error()),
+    };
+
+    let index = repo.index().map_err(to_io_error)?;
+    let mut last = None;
+
+    for entry in index.iter() {
+        let file = workdir.join(path_from_bytes(&entry.path));
+
+        // Conflicted files have an entry per stage, with the same path
+        if last.as_ref() == Some(&file) {
+            continue;
+        }
+
+        if entry.mode == GITLINK_MODE {
+            if let Ok(submodule) = Repository::open(&file) {
+                collect_tracked(&submodule, tracked)?;
+            }
+        } else {
+            tracked.push(file.clone());
+        }
+
+        last = Some(file);
+    }
+
+    Ok(())
+}
+
+fn bare_error() -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        "Bare repositories have no tracked files",
+    )
+}
+
+/// Converts a git error, keeping "not found" errors recognisable.
+pub fn to_io_error(e: git2::Error) -> Error {
+    let kind = match e.code() {
+        ErrorCode::NotFound => ErrorKind::NotFound,
+        ErrorCode::Ambiguous | ErrorCode::InvalidSpec => ErrorKind::InvalidInput,
+        _ => ErrorKind::Other,
+    };
+
+    Error::new(kind, e.message().to_string())
+}
+
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+pub(crate) mod git_tests {
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::path::{Path, PathBuf};
+
+    use git2::{Oid, Repository, Signature};
+
+    use super::{tracked_files, Snapshot};
+
+    /// Writes `files` into the repository at `dir` and commits them,
+    /// creating the repository first if needed.
+    pub fn commit<C: AsRef<[u8]>>(dir: &Path, files: &[(&str, C)], message: &str) -> Oid {
+        let repo = Repository::open(dir)
+            .or_else(|_| Repository::init(dir))
+            .unwrap();
+        let mut index = repo.index().unwrap();
+
+        for (name, contents) in files {
+            let file = dir.join(name);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(&file, contents).unwrap();
+            index.add_path(Path::new(name)).unwrap();
+        }
+
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let author = Signature::now("Ada", "ada@example.com").unwrap();
+        let parent = repo.head().ok().map(|h| h.peel_to_commit().unwrap());
+        let parents = parent.iter().collect::<Vec<_>>();
+
+        repo.commit(Some("HEAD"), &author, &author, message, &tree, &parents)
+            .unwrap()
+    }
+
+    #[test]
+    fn git_tracked_files() {
+        let dir = tempfile::tempdir().unwrap();
+        commit(
+            dir.path(),
+            &[("src/main.rs", "fn main() {}\n"), ("README.md", "# hi\n")],
+            "init",
+        );
+        fs::write(dir.path().join("src/untracked.rs"), "\n").unwrap();
+
+        let mut files = tracked_files(dir.path()).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![dir.path().join("README.md"), dir.path().join("src/main.rs")]
+        );
+
+        let src = dir.path().join("src");
+        assert_eq!(tracked_files(&src).unwrap(), vec![src.join("main.rs")]);
+    }
+
+    #[test]
+    fn git_tracked_files_submodule() {
+        let dir = tempfile::tempdir().unwrap();
+        commit(dir.path(), &[("main.rs", "\n")], "init");
+
+        // A checked out submodule, added to the index as a gitlink
+        let inner = dir.path().join("vendor/lib");
+        let head = commit(&inner, &[("lib.rs", "\n")], "init");
+        let repo = Repository::open(dir.path()).unwrap();
+        let mut index = repo.index().unwrap();
+        let entry = git2::IndexEntry {
+            ctime: git2::IndexTime::new(0, 0),
+            mtime: git2::IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: super::GITLINK_MODE,
+            uid: 0,
+            gid: 0,
+            file_size: 0,
+            id: head,
+            flags: 0,
+            flags_extended: 0,
+            path: b"vendor/lib".to_vec(),
+        };
+        index.add(&entry).unwrap();
+        index.write().unwrap();
+
+        let mut files = tracked_files(dir.path()).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![dir.path().join("main.rs"), inner.join("lib.rs")]
+        );
+    }
+
+    #[test]
+    fn git_tracked_files_worktree() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("main");
+        commit(&main, &[("a.rs", "\n")], "init");
+
+        let repo = Repository::open(&main).unwrap();
+        let linked = dir.path().join("linked");
+        repo.worktree("linked", &linked, None).unwrap();
+        commit(&linked, &[("b.rs", "\n")], "add b");
+
+        let files: Vec<PathBuf> = tracked_files(&linked).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(tracked_files(&main).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn git_tracked_files_not_a_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let e = tracked_files(dir.path()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn git_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        commit(
+            dir.path(),
+            &[("src/main.rs", "fn main() {}\n"), ("README.md", "# hi\n")],
+            "init",
+        );
+        commit(dir.path(), &[("src/main.rs", "\n")], "empty main");
+        fs::remove_file(dir.path().join("README.md")).unwrap();
+
+        let snapshot = Snapshot::new(dir.path(), "HEAD~1").unwrap();
+        let main = dir.path().join("src/main.rs");
+        assert_eq!(
+            snapshot.files(),
+            vec![dir.path().join("README.md"), main.clone()]
+        );
+        assert_eq!(snapshot.tree_path(&main), Some(Path::new("src/main.rs")));
+
+        let repo = snapshot.open().unwrap();
+        assert_eq!(
+            snapshot.blob(&repo, &main).unwrap().content(),
+            b"fn main() {}\n"
+        );
+
+        let src = dir.path().join("src");
+        let snapshot = Snapshot::new(&src, "HEAD").unwrap();
+        assert_eq!(snapshot.files(), vec![main.clone()]);
+        assert_eq!(snapshot.blob(&repo, &main).unwrap().content(), b"\n");
+    }
+
+    #[test]
+    fn git_snapshot_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        commit(dir.path(), &[("main.rs", "\n")], "init");
+
+        let e = Snapshot::new(dir.path(), "v9.9.9").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        let e = Snapshot::new(&dir.path().join("docs"), "HEAD").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn git_snapshot_deleted_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        commit(
+            dir.path(),
+            &[("old/main.rs", "\n"), ("main.rs", "\n")],
+            "init",
+        );
+        fs::remove_dir_all(dir.path().join("old")).unwrap();
+
+        let old = dir.path().join("old");
+        let snapshot = Snapshot::new(&old, "HEAD").unwrap();
+        assert_eq!(snapshot.files(), vec![old.join("main.rs")]);
+        assert_eq!(
+            snapshot.tree_path(&old.join("main.rs")),
+            Some(Path::new("old/main.rs"))
+        );
+
+        // Still relative to the nearest directory that exists
+        let snapshot = Snapshot::new(&dir.path().join("gone/../old"), "HEAD").unwrap();
+        assert_eq!(snapshot.files().len(), 1);
+    }
+}
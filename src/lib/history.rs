@@ -0,0 +1,576 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use git2::{Commit, ObjectType, Oid, Repository, Sort, Tree};
+
+use super::git::{self, to_io_error, SYMLINK_MODE};
+use super::language;
+use super::summary::{SkipReason, Skipped};
+
+/// Which commits of a repository's history are counted.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Sampling {
+    /// Every commit along the first parent of each merge.
+    Commit,
+
+    /// The last commit of each day, in UTC.
+    Day,
+
+    /// The last commit of each week, starting on Monday, in UTC.
+    Week,
+
+    /// Every tagged commit.
+    Tag,
+}
+
+impl Default for Sampling {
+    /// ```
+    /// use xloc::Sampling;
+    ///
+    /// assert_eq!(Sampling::default(), Sampling::Commit);
+    /// ```
+    fn default() -> Self {
+        Self::Commit
+    }
+}
+
+impl FromStr for Sampling {
+    type Err = Error;
+
+    /// ```
+    /// use xloc::Sampling;
+    ///
+    /// assert_eq!("week".parse::<Sampling>().unwrap(), Sampling::Week);
+    /// assert!("hour".parse::<Sampling>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "commit" => Ok(Self::Commit),
+            "day" => Ok(Self::Day),
+            "week" => Ok(Self::Week),
+            "tag" => Ok(Self::Tag),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown sampling: {}", s),
+            )),
+        }
+    }
+}
+
+/// The count of each language at one commit.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Point {
+    /// The full id of the commit.
+    pub commit: String,
+
+    /// When the commit was made, in seconds since the Unix epoch.
+    pub time: i64,
+
+    /// The tag the commit was sampled for, if sampling by tag.
+    pub tag: Option<String>,
+
+    /// The count of each language, keyed by language name.
+    pub languages: BTreeMap<String, usize>,
+}
+
+impl Point {
+    /// The count of every language combined.
+    pub fn total(&self) -> usize {
+        self.languages.values().sum()
+    }
+}
+
+/// Counts at a series of commits, oldest first.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct History {
+    pub points: Vec<Point>,
+
+    /// Files that couldn't be counted, and why, once for each version
+    /// of a file.
+    pub skipped: Vec<Skipped>,
+}
+
+impl History {
+    /// Formats the history as CSV, with a row for each language at each
+    /// commit.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("commit,date,tag,language,count\n");
+
+        for point in &self.points {
+            let tag = point.tag.as_deref().map(csv_field).unwrap_or_default();
+
+            for (language, count) in &point.languages {
+                let date = format_time(point.time);
+                let language = csv_field(language);
+                writeln!(
+                    csv,
+                    "{},{},{},{},{}",
+                    point.commit, date, tag, language, count
+                )
+                .unwrap();
+            }
+        }
+
+        csv
+    }
+
+    /// Formats the history as a JSON array, with an object for each
+    /// commit.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("[");
+
+        for (i, point) in self.points.iter().enumerate() {
+            let tag = point.tag.as_deref().map_or("null".to_string(), json_string);
+            let languages = point
+                .languages
+                .iter()
+                .map(|(language, count)| format!("{}: {}", json_string(language), count))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            json.push_str(if i == 0 { "\n" } else { ",\n" });
+            write!(
+                json,
+                "  {{\"commit\": \"{}\", \"date\": \"{}\", \"tag\": {}, \"languages\": {{{}}}, \"total\": {}}}",
+                point.commit,
+                format_time(point.time),
+                tag,
+                languages,
+                point.total()
+            )
+            .unwrap();
+        }
+
+        let end = match self.points.is_empty() {
+            true => "]\n",
+            false => "\n]\n",
+        };
+
+        json.push_str(end);
+        json
+    }
+}
+
+/// Counts the files of `path` at each sampled commit reachable from
+/// `rev`. Only files `keep` accepts are counted, by `count`, which gets
+/// a file's name and contents and returns why it was skipped otherwise.
+/// Files over `max_size` bytes are skipped without reading them.
+///
+/// Counts are cached by tree and blob id, so only the parts of the
+/// tree that changed between two commits are walked and counted again.
+pub fn walk<K, C>(
+    path: &Path,
+    rev: &str,
+    sampling: Sampling,
+    max_size: Option<u64>,
+    keep: K,
+    count: C,
+) -> Result<History>
+where
+    K: Fn(&Path) -> bool,
+    C: FnMut(&Path, &[u8]) -> std::result::Result<usize, SkipReason>,
+{
+    let (repo, prefix) = git::discover(path)?;
+    let start = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(to_io_error)?;
+
+    let mut walker = Walker {
+        repo: &repo,
+        path,
+        max_size,
+        keep,
+        count,
+        trees: HashMap::new(),
+        blobs: HashMap::new(),
+        skipped: vec![],
+    };
+
+    let mut history = History::default();
+
+    for (commit, tag) in sample(&repo, &start, sampling).map_err(to_io_error)? {
+        let tree = commit.tree().map_err(to_io_error)?;
+
+        let languages = match prefix.as_os_str().is_empty() {
+            true => walker.count_tree(&tree, Path::new(""))?,
+            false => match tree.get_path(&prefix) {
+                Ok(entry) if entry.kind() == Some(ObjectType::Tree) => {
+                    let subtree = repo.find_tree(entry.id()).map_err(to_io_error)?;
+                    walker.count_tree(&subtree, Path::new(""))?
+                }
+                _ => BTreeMap::new(),
+            },
+        };
+
+        history.points.push(Point {
+            commit: commit.id().to_string(),
+            time: commit.time().seconds(),
+            tag,
+            languages,
+        });
+    }
+
+    history.skipped = walker.skipped;
+    Ok(history)
+}
+
+/// Walks trees, remembering the count of every tree and blob seen.
+struct Walker<'a, K, C> {
+    repo: &'a Repository,
+    path: &'a Path,
+    max_size: Option<u64>,
+    keep: K,
+    count: C,
+
+    /// The counts of each tree, by its id and its path in the revision,
+    /// as globs and languages depend on where a tree is.
+    trees: HashMap<(Oid, PathBuf), BTreeMap<String, usize>>,
+
+    /// The count of each blob, by its id and file name, or [None] if it
+    /// was skipped.
+    blobs: HashMap<(Oid, OsString), Option<usize>>,
+
+    /// Each version of a file that was skipped, when it was first seen.
+    skipped: Vec<Skipped>,
+}
+
+impl<K, C> Walker<'_, K, C>
+where
+    K: Fn(&Path) -> bool,
+    C: FnMut(&Path, &[u8]) -> std::result::Result<usize, SkipReason>,
+{
+    /// Counts the files of `tree`, which is at `dir` below the path.
+    fn count_tree(&mut self, tree: &Tree, dir: &Path) -> Result<BTreeMap<String, usize>> {
+        let key = (tree.id(), dir.to_owned());
+
+        if let Some(languages) = self.trees.get(&key) {
+            return Ok(languages.clone());
+        }
+
+        let mut languages = BTreeMap::new();
+
+        for entry in tree.iter() {
+            let name = git::path_from_bytes(entry.name_bytes());
+            let inner = dir.join(&name);
+
+            match entry.kind() {
+                Some(ObjectType::Tree) => {
+                    let subtree = self.repo.find_tree(entry.id()).map_err(to_io_error)?;
+
+                    for (language, count) in self.count_tree(&subtree, &inner)? {
+                        *languages.entry(language).or_default() += count;
+                    }
+                }
+                Some(ObjectType::Blob) if entry.filemode() != SYMLINK_MODE => {
+                    let file = self.path.join(&inner);
+
+                    if !(self.keep)(&file) {
+                        continue;
+                    }
+
+                    let blob_key = (entry.id(), name.into_os_string());
+                    let counted = match self.blobs.get(&blob_key) {
+                        Some(counted) => *counted,
+                        None => {
+                            let counted = match self.too_large(entry.id())? {
+                                true => Err(SkipReason::TooLarge),
+                                false => {
+                                    let blob =
+                                        self.repo.find_blob(entry.id()).map_err(to_io_error)?;
+                                    (self.count)(&file, blob.content())
+                                }
+                            };
+
+                            let counted = match counted {
+                                Ok(count) => Some(count),
+                                Err(reason) => {
+                                    self.skipped.push(Skipped::new(file, reason));
+                                    None
+                                }
+                            };
+
+                            self.blobs.insert(blob_key, counted);
+                            counted
+                        }
+                    };
+
+                    if let Some(count) = counted {
+                        let language = language::name_of(&inner).to_string();
+                        *languages.entry(language).or_default() += count;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        self.trees.insert(key, languages.clone());
+        Ok(languages)
+    }
+
+    /// Whether a blob is over the size limit, from its header only, as
+    /// git inflates the contents of a blob whole.
+    fn too_large(&self, id: Oid) -> Result<bool> {
+        match self.max_size {
+            Some(max) => {
+                let odb = self.repo.odb().map_err(to_io_error)?;
+                let (size, _) = odb.read_header(id).map_err(to_io_error)?;
+                Ok(size as u64 > max)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Picks the commits to count, oldest first, with the tag each one was
+/// picked for when sampling by tag.
+fn sample<'r>(
+    repo: &'r Repository,
+    start: &Commit,
+    sampling: Sampling,
+) -> std::result::Result<Vec<(Commit<'r>, Option<String>)>, git2::Error> {
+    if let Sampling::Tag = sampling {
+        let mut tagged = vec![];
+
+        for name in repo.tag_names(None)?.iter().flatten() {
+            let reference = repo.find_reference(&format!("refs/tags/{}", name))?;
+            let commit = match reference.peel_to_commit() {
+                Ok(commit) => commit,
+                Err(_) => continue,
+            };
+
+            if commit.id() == start.id() || repo.graph_descendant_of(start.id(), commit.id())? {
+                tagged.push((commit, Some(name.to_string())));
+            }
+        }
+
+        tagged.sort_by_key(|(commit, tag)| (commit.time().seconds(), tag.clone()));
+        return Ok(tagged);
+    }
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(start.id())?;
+    revwalk.simplify_first_parent()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+
+    let mut commits: Vec<(Commit, Option<String>)> = vec![];
+    let mut last_period = None;
+
+    for id in revwalk {
+        let commit = repo.find_commit(id?)?;
+        let days = commit.time().seconds().div_euclid(86_400);
+
+        // 1970-01-01 was a Thursday, so the first Monday is 4 days later
+        let period = match sampling {
+            Sampling::Day => Some(days),
+            Sampling::Week => Some((days - 4).div_euclid(7)),
+            _ => None,
+        };
+
+        // Each commit replaces an earlier one in the same period
+        if period.is_some() && period == last_period {
+            commits.pop();
+        }
+
+        last_period = period;
+        commits.push((commit, None));
+    }
+
+    Ok(commits)
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 UTC timestamp.
+fn format_time(seconds: i64) -> String {
+    let days = seconds.div_euclid(86_400);
+    let secs = seconds.rem_euclid(86_400);
+
+    // Converts days to a civil date, from Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Quotes a CSV field if it needs to be.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// Quotes and escapes a JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod history_tests {
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    use git2::{Repository, Time};
+
+    use super::{format_time, walk, History, Point, Sampling};
+    use crate::git::git_tests::commit;
+    use crate::summary::{SkipReason, Skipped};
+
+    fn lines(_: &Path, content: &[u8]) -> Result<usize, SkipReason> {
+        Ok(content.iter().filter(|b| **b == b'\n').count())
+    }
+
+    #[test]
+    fn history_format_time() {
+        assert_eq!(format_time(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_time(951_782_400 + 3_661), "2000-02-29T01:01:01Z");
+        assert_eq!(format_time(-1), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn history_walk() {
+        let dir = tempfile::tempdir().unwrap();
+        commit(dir.path(), &[("main.rs", "a\n"), ("run.py", "b\n")], "one");
+        commit(dir.path(), &[("main.rs", "a\nb\n")], "two");
+        let head = commit(dir.path(), &[("src/lib.rs", "c\n")], "three");
+
+        let repo = Repository::open(dir.path()).unwrap();
+        let tagged = repo.find_object(head, None).unwrap();
+        repo.tag_lightweight("v1", &tagged, false).unwrap();
+
+        let mut counted = 0;
+        let history = walk(
+            dir.path(),
+            "HEAD",
+            Sampling::Commit,
+            None,
+            |_| true,
+            |path, content| {
+                counted += 1;
+                lines(path, content)
+            },
+        )
+        .unwrap();
+
+        let totals = history.points.iter().map(Point::total).collect::<Vec<_>>();
+        assert_eq!(totals, vec![2, 3, 4]);
+        assert_eq!(history.points[2].commit, head.to_string());
+        assert_eq!(history.points[2].languages["Rust"], 3);
+
+        // Each version of each file is only counted once
+        assert_eq!(counted, 4);
+
+        let history = walk(dir.path(), "HEAD", Sampling::Tag, None, |_| true, lines).unwrap();
+        assert_eq!(history.points.len(), 1);
+        assert_eq!(history.points[0].tag.as_deref(), Some("v1"));
+
+        // Unless the commits straddle midnight, they were made on one day
+        let history = walk(dir.path(), "HEAD", Sampling::Day, None, |_| true, lines).unwrap();
+        assert!(history.points.len() <= 2);
+        assert_eq!(history.points.last().unwrap().total(), 4);
+
+        let src = dir.path().join("src");
+        let history = walk(&src, "HEAD", Sampling::Commit, None, |_| true, lines).unwrap();
+        let totals = history.points.iter().map(Point::total).collect::<Vec<_>>();
+        assert_eq!(totals, vec![0, 0, 1]);
+
+        let keep = |file: &Path| file.extension().is_some_and(|e| e == "py");
+        let history = walk(dir.path(), "HEAD", Sampling::Commit, None, keep, lines).unwrap();
+        assert_eq!(
+            history.points[2].languages.keys().collect::<Vec<_>>(),
+            ["Python"]
+        );
+    }
+
+    #[test]
+    fn history_walk_too_large() {
+        let dir = tempfile::tempdir().unwrap();
+        commit(
+            dir.path(),
+            &[("main.rs", "a\n"), ("big.rs", &"b\n".repeat(64))],
+            "one",
+        );
+
+        let mut counted = vec![];
+        let history = walk(
+            dir.path(),
+            "HEAD",
+            Sampling::Commit,
+            Some(16),
+            |_| true,
+            |path, content| {
+                counted.push(path.file_name().unwrap().to_owned());
+                lines(path, content)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(history.points[0].total(), 1);
+        assert_eq!(counted, ["main.rs"]);
+        assert_eq!(
+            history.skipped,
+            [Skipped::new(
+                dir.path().join("big.rs"),
+                SkipReason::TooLarge
+            )]
+        );
+    }
+
+    #[test]
+    fn history_output() {
+        let history = History {
+            points: vec![Point {
+                commit: "abc".to_string(),
+                time: Time::new(86_400, 0).seconds(),
+                tag: Some("v1,0".to_string()),
+                languages: BTreeMap::from([("C#".to_string(), 2), ("Rust".to_string(), 3)]),
+            }],
+            ..History::default()
+        };
+
+        assert_eq!(
+            history.to_csv(),
+            "commit,date,tag,language,count\n\
+             abc,1970-01-02T00:00:00Z,\"v1,0\",C#,2\n\
+             abc,1970-01-02T00:00:00Z,\"v1,0\",Rust,3\n"
+        );
+
+        assert_eq!(
+            history.to_json(),
+            "[\n  {\"commit\": \"abc\", \"date\": \"1970-01-02T00:00:00Z\", \"tag\": \"v1,0\", \
+             \"languages\": {\"C#\": 2, \"Rust\": 3}, \"total\": 5}\n]\n"
+        );
+
+        assert_eq!(History::default().to_json(), "[]\n");
+    }
+}
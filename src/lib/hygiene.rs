@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use super::summary::Skipped;
+
+/// The number of columns a tab advances to when measuring indentation.
+pub const TAB_WIDTH: usize = 4;
+
+/// Whitespace statistics for a file, or a group of files.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Hygiene {
+    /// The number of files these statistics cover.
+    pub files: usize,
+
+    /// The number of lines scanned.
+    pub lines: usize,
+
+    /// Lines ending in a space or tab, including whitespace-only lines.
+    pub trailing_whitespace: usize,
+
+    /// Lines indented only with tabs.
+    pub tab_indented: usize,
+
+    /// Lines indented only with spaces.
+    pub space_indented: usize,
+
+    /// Lines whose indentation contains both tabs and spaces.
+    pub mixed_indented: usize,
+
+    /// Files that use more than one indentation style.
+    pub mixed_files: usize,
+
+    /// The widest indentation seen, in columns, with tabs advancing to
+    /// the next multiple of [TAB_WIDTH].
+    pub max_indent: usize,
+}
+
+impl Hygiene {
+    /// Scans the text of a single file.
+    pub fn scan(text: &str) -> Self {
+        let mut scanner = Scanner::new();
+        scanner.scan(text);
+        scanner.finish()
+    }
+
+    /// Adds another set of statistics into this one.
+    pub fn add(&mut self, other: &Self) {
+        self.files += other.files;
+        self.lines += other.lines;
+        self.trailing_whitespace += other.trailing_whitespace;
+        self.tab_indented += other.tab_indented;
+        self.space_indented += other.space_indented;
+        self.mixed_indented += other.mixed_indented;
+        self.mixed_files += other.mixed_files;
+        self.max_indent = self.max_indent.max(other.max_indent);
+    }
+}
+
+/// Scans the text of a single file in pieces, which may split a line
+/// anywhere, like the chunks of a [Stream](crate::stream::Stream).
+#[derive(Debug)]
+pub(crate) struct Scanner {
+    hygiene: Hygiene,
+    line: Option<Line>,
+}
+
+/// What is known about the line being scanned so far.
+#[derive(Debug, Default)]
+struct Line {
+    tabs: bool,
+    spaces: bool,
+    width: usize,
+    indented: bool,
+    blank: bool,
+    trailing: bool,
+}
+
+impl Scanner {
+    pub(crate) fn new() -> Self {
+        Self {
+            hygiene: Hygiene {
+                files: 1,
+                ..Hygiene::default()
+            },
+            line: None,
+        }
+    }
+
+    /// Scans the next piece of the file.
+    pub(crate) fn scan(&mut self, text: &str) {
+        for piece in text.split_inclusive('\n') {
+            let (content, ended) = match piece.strip_suffix('\n') {
+                Some(content) => (content.strip_suffix('\r').unwrap_or(content), true),
+                None => (piece, false),
+            };
+
+            let line = self.line.get_or_insert_with(|| Line {
+                blank: true,
+                ..Line::default()
+            });
+
+            for c in content.chars() {
+                if !line.indented {
+                    match c {
+                        ' ' => line.spaces = true,
+                        '\t' => line.tabs = true,
+                        _ => line.indented = true,
+                    }
+
+                    line.width = match c {
+                        ' ' => line.width + 1,
+                        '\t' => (line.width / TAB_WIDTH + 1) * TAB_WIDTH,
+                        _ => line.width,
+                    };
+                }
+
+                line.blank &= c.is_whitespace();
+
+                // A piece may end between the \r and \n of a line ending
+                if c != '\r' {
+                    line.trailing = matches!(c, ' ' | '\t');
+                }
+            }
+
+            if ended {
+                self.end_line();
+            }
+        }
+    }
+
+    /// Finishes the statistics once every piece has been scanned.
+    pub(crate) fn finish(mut self) -> Hygiene {
+        self.end_line();
+
+        let h = &mut self.hygiene;
+        let styles = [h.tab_indented, h.space_indented, h.mixed_indented];
+
+        if styles.iter().filter(|n| **n > 0).count() > 1 {
+            h.mixed_files = 1;
+        }
+
+        self.hygiene
+    }
+
+    fn end_line(&mut self) {
+        let Some(line) = self.line.take() else {
+            return;
+        };
+
+        let h = &mut self.hygiene;
+        h.lines += 1;
+
+        if line.trailing {
+            h.trailing_whitespace += 1;
+        }
+
+        if line.blank {
+            return;
+        }
+
+        match (line.tabs, line.spaces) {
+            (true, true) => h.mixed_indented += 1,
+            (true, false) => h.tab_indented += 1,
+            (false, true) => h.space_indented += 1,
+            (false, false) => (),
+        }
+
+        h.max_indent = h.max_indent.max(line.width);
+    }
+}
+
+/// A whitespace hygiene report, broken down per file and per language.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct HygieneReport {
+    /// Statistics for each file that was scanned.
+    pub files: BTreeMap<PathBuf, Hygiene>,
+
+    /// Statistics for each language, keyed by language name.
+    pub languages: BTreeMap<String, Hygiene>,
+
+    /// Statistics for every file combined.
+    pub total: Hygiene,
+
+    /// Files that couldn't be scanned, and why.
+    pub skipped: Vec<Skipped>,
+}
+
+impl HygieneReport {
+    /// Records the statistics for a single file.
+    pub fn insert(&mut self, file: PathBuf, language: &str, hygiene: Hygiene) {
+        self.languages
+            .entry(language.to_string())
+            .or_default()
+            .add(&hygiene);
+
+        self.total.add(&hygiene);
+        self.files.insert(file, hygiene);
+    }
+
+    /// Merges another report into this one.
+    pub fn merge(&mut self, other: Self) {
+        for (language, hygiene) in other.languages {
+            self.languages.entry(language).or_default().add(&hygiene);
+        }
+
+        self.total.add(&other.total);
+        self.files.extend(other.files);
+        self.skipped.extend(other.skipped);
+    }
+}
+
+fn write_header(f: &mut fmt::Formatter, title: &str) -> fmt::Result {
+    writeln!(
+        f,
+        "{:<40} {:>6} {:>8} {:>9} {:>6} {:>7} {:>6} {:>11} {:>7}",
+        title, "Files", "Lines", "Trailing", "Tabs", "Spaces", "Mixed", "Mixed files", "Indent"
+    )
+}
+
+fn write_row(f: &mut fmt::Formatter, name: &str, h: &Hygiene) -> fmt::Result {
+    writeln!(
+        f,
+        "{:<40} {:>6} {:>8} {:>9} {:>6} {:>7} {:>6} {:>11} {:>7}",
+        name,
+        h.files,
+        h.lines,
+        h.trailing_whitespace,
+        h.tab_indented,
+        h.space_indented,
+        h.mixed_indented,
+        h.mixed_files,
+        h.max_indent
+    )
+}
+
+impl fmt::Display for HygieneReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_header(f, "File")?;
+
+        for (file, hygiene) in &self.files {
+            write_row(f, &file.display().to_string(), hygiene)?;
+        }
+
+        writeln!(f)?;
+        write_header(f, "Language")?;
+
+        for (language, hygiene) in &self.languages {
+            write_row(f, language, hygiene)?;
+        }
+
+        write_row(f, "Total", &self.total)
+    }
+}
+
+#[cfg(test)]
+mod hygiene_tests {
+    use std::path::PathBuf;
+
+    use super::{Hygiene, HygieneReport, Scanner};
+
+    #[test]
+    fn hygiene_scan_trailing() {
+        let result = Hygiene::scan("fn main() { \n\t\n}\r\n");
+        assert_eq!(result.lines, 3);
+        assert_eq!(result.trailing_whitespace, 2);
+    }
+
+    #[test]
+    fn hygiene_scan_indentation() {
+        let result = Hygiene::scan("a\n\tb\n\t\tc\n    d\n\t  e\n");
+        assert_eq!(result.tab_indented, 2);
+        assert_eq!(result.space_indented, 1);
+        assert_eq!(result.mixed_indented, 1);
+        assert_eq!(result.mixed_files, 1);
+        assert_eq!(result.max_indent, 8);
+    }
+
+    #[test]
+    fn hygiene_scan_consistent() {
+        let result = Hygiene::scan("a\n  b\n    c\n\n");
+        assert_eq!(result.space_indented, 2);
+        assert_eq!(result.mixed_files, 0);
+        assert_eq!(result.max_indent, 4);
+    }
+
+    #[test]
+    fn hygiene_scan_pieces() {
+        let text = "fn main() { \r\n\t  let x = 1;\t\n\n    y\n";
+        let whole = Hygiene::scan(text);
+
+        // Pieces that split lines anywhere give the same statistics
+        for split in 1..text.len() {
+            let mut scanner = Scanner::new();
+            scanner.scan(&text[..split]);
+            scanner.scan(&text[split..]);
+            assert_eq!(scanner.finish(), whole, "split at {}", split);
+        }
+
+        assert_eq!(whole.lines, 4);
+        assert_eq!(whole.trailing_whitespace, 2);
+        assert_eq!(whole.mixed_indented, 1);
+    }
+
+    #[test]
+    fn hygiene_report_merge() {
+        let mut first = HygieneReport::default();
+        first.insert(PathBuf::from("a.rs"), "Rust", Hygiene::scan("\tx\n"));
+
+        let mut second = HygieneReport::default();
+        second.insert(PathBuf::from("b.rs"), "Rust", Hygiene::scan("  x \n"));
+        second.insert(PathBuf::from("c.py"), "Python", Hygiene::scan("x\n"));
+
+        first.merge(second);
+        assert_eq!(first.files.len(), 3);
+        assert_eq!(first.languages["Rust"].files, 2);
+        assert_eq!(first.languages["Rust"].tab_indented, 1);
+        assert_eq!(first.languages["Rust"].space_indented, 1);
+        assert_eq!(first.total.trailing_whitespace, 1);
+        assert_eq!(first.total.lines, 3);
+    }
+}
@@ -0,0 +1,296 @@
+use std::io::{Error, ErrorKind, Result};
+use std::path::{self, Path};
+use std::rc::Rc;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+/// The names of the directories that are left out of every scan by
+/// default: version control metadata, then build outputs and caches.
+pub const DEFAULT_EXCLUDES: [&str; 9] = [
+    ".git",
+    ".hg",
+    ".svn",
+    ".bzr",
+    "_darcs",
+    "CVS",
+    "target",
+    "node_modules",
+    "__pycache__",
+];
+
+/// The ignore files read from each directory, from lowest to highest
+/// precedence.
+pub const IGNORE_FILES: [&str; 3] = [".gitignore", ".ignore", ".xlocignore"];
+
+/// The gitignore style rules that apply inside a directory during a
+/// scan. Rules from deeper directories take precedence over the ones
+/// above them, which take precedence over the repository's
+/// `.git/info/exclude` and the user's global excludes file.
+#[derive(Clone, Default)]
+pub struct Ignores {
+    matchers: Vec<Rc<Gitignore>>,
+}
+
+impl Ignores {
+    /// Collects the rules that apply to a scan starting at `root`: the
+    /// global excludes, the repository excludes and the ignore files of
+    /// every directory above `root`, up to the root of its repository.
+    pub fn root(root: &Path) -> Self {
+        let mut ignores = Self::default();
+        ignores.push(Gitignore::global().0);
+
+        let root = match path::absolute(root) {
+            Ok(root) => root,
+            Err(_) => return ignores,
+        };
+
+        // The root may be the repository itself, but its own ignore
+        // files are read when the scan descends into it
+        let ancestors = root.ancestors().collect::<Vec<_>>();
+        let repo = ancestors.iter().position(|p| p.join(".git").exists());
+
+        let parents = match repo {
+            Some(repo) => {
+                let exclude = ancestors[repo].join(".git/info/exclude");
+                let mut builder = GitignoreBuilder::new(ancestors[repo]);
+                builder.add(exclude);
+
+                if let Ok(matcher) = builder.build() {
+                    ignores.push(matcher);
+                }
+
+                &ancestors[1..=repo]
+            }
+            None => &[][..],
+        };
+
+        parents
+            .iter()
+            .rev()
+            .fold(ignores, |ignores, dir| ignores.descend(dir))
+    }
+
+    /// Adds the rules from the ignore files in `dir`, for scanning its
+    /// entries.
+    pub fn descend(&self, dir: &Path) -> Self {
+        let mut ignores = self.clone();
+        let dir = path::absolute(dir).unwrap_or_else(|_| dir.to_owned());
+
+        for name in IGNORE_FILES {
+            let file = dir.join(name);
+
+            if !file.is_file() {
+                continue;
+            }
+
+            // An unreadable or invalid ignore file is treated as empty,
+            // rather than failing the whole scan
+            let mut builder = GitignoreBuilder::new(&dir);
+            builder.add(file);
+
+            if let Ok(matcher) = builder.build() {
+                ignores.push(matcher);
+            }
+        }
+
+        ignores
+    }
+
+    /// Whether `path` is ignored by any of the rules, letting the most
+    /// specific rule win.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.matchers.is_empty() {
+            return false;
+        }
+
+        let path = path::absolute(path).unwrap_or_else(|_| path.to_owned());
+
+        for matcher in self.matchers.iter().rev() {
+            match matcher.matched(&path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => continue,
+            }
+        }
+
+        false
+    }
+
+    fn push(&mut self, matcher: Gitignore) {
+        if !matcher.is_empty() {
+            self.matchers.push(Rc::new(matcher));
+        }
+    }
+}
+
+/// Include and exclude globs given by the user, matched against paths
+/// relative to the scan root.
+///
+/// Globs follow `.gitignore` syntax: a glob without a `/` matches any
+/// path component, like `*.rs` or `node_modules`, while one with a `/`
+/// matches the full relative path, like `docs/**`. A leading `!`
+/// negates a glob, and the last matching glob wins.
+#[derive(Clone, Default)]
+pub struct Globs {
+    include: Option<Rc<Gitignore>>,
+    exclude: Option<Rc<Gitignore>>,
+}
+
+impl Globs {
+    /// Builds the globs for a scan of `root`, failing with
+    /// [ErrorKind::InvalidInput] if any glob is invalid.
+    pub fn new(root: &Path, include: &[String], exclude: &[String]) -> Result<Self> {
+        let root = path::absolute(root)?;
+
+        Ok(Self {
+            include: build(&root, include)?,
+            exclude: build(&root, exclude)?,
+        })
+    }
+
+    /// Whether `path` is left out of the scan, either because it matches
+    /// an exclude glob, or because it is a file that matches none of the
+    /// include globs. Directories are never left out by include globs,
+    /// so `*.rs` still finds files in subdirectories.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        if self.include.is_none() && self.exclude.is_none() {
+            return false;
+        }
+
+        let path = path::absolute(path).unwrap_or_else(|_| path.to_owned());
+
+        if let Some(exclude) = &self.exclude {
+            if exclude.matched(&path, is_dir).is_ignore() {
+                return true;
+            }
+        }
+
+        match &self.include {
+            Some(include) if !is_dir => !include
+                .matched_path_or_any_parents(&path, is_dir)
+                .is_ignore(),
+            _ => false,
+        }
+    }
+}
+
+/// The [DEFAULT_EXCLUDES] as owned strings, so they can be overridden.
+pub fn default_excludes() -> Vec<String> {
+    DEFAULT_EXCLUDES.iter().map(|e| e.to_string()).collect()
+}
+
+/// Whether `path` is named like one of the `excludes`, such as the
+/// [DEFAULT_EXCLUDES].
+pub fn is_excluded_name<S: AsRef<str>>(path: &Path, excludes: &[S]) -> bool {
+    match path.file_name().and_then(|n| n.to_str()) {
+        Some(name) => excludes.iter().any(|e| e.as_ref() == name),
+        None => false,
+    }
+}
+
+/// Whether a file or directory is hidden, by the Unix convention of
+/// names starting with a `.`.
+pub fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with('.'))
+}
+
+fn build(root: &Path, globs: &[String]) -> Result<Option<Rc<Gitignore>>> {
+    if globs.is_empty() {
+        return Ok(None);
+    }
+
+    let invalid = |e| Error::new(ErrorKind::InvalidInput, format!("Invalid glob: {}", e));
+    let mut builder = GitignoreBuilder::new(root);
+
+    for glob in globs {
+        builder.add_line(None, glob).map_err(invalid)?;
+    }
+
+    Ok(Some(Rc::new(builder.build().map_err(invalid)?)))
+}
+
+#[cfg(test)]
+mod ignores_tests {
+    use std::io::ErrorKind;
+    use std::path::Path;
+
+    use super::{is_excluded_name, Globs, Ignores, DEFAULT_EXCLUDES};
+
+    #[test]
+    fn ignores_descend() {
+        let root = Path::new("tests/ignore");
+        let ignores = Ignores::default().descend(root);
+
+        assert!(ignores.is_ignored(&root.join("debug.log"), false));
+        assert!(ignores.is_ignored(&root.join("build"), true));
+        assert!(ignores.is_ignored(&root.join("vendor"), true));
+        assert!(!ignores.is_ignored(&root.join("main.rs"), false));
+
+        // Whitelisted by .ignore, over the .gitignore
+        assert!(!ignores.is_ignored(&root.join("keep.log"), false));
+    }
+
+    #[test]
+    fn ignores_root_reads_parents() {
+        let ignores = Ignores::root(Path::new("tests/ignore/nested"));
+        assert!(ignores.is_ignored(Path::new("tests/ignore/nested/trace.log"), false));
+        assert!(!ignores.is_ignored(Path::new("tests/ignore/nested/mod.rs"), false));
+    }
+
+    #[test]
+    fn ignores_root_reads_repo_exclude() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        std::fs::write(dir.path().join(".git/info/exclude"), "secret.rs\n").unwrap();
+
+        // Scanning from the root of the repository, and from inside it
+        for root in [dir.path().to_owned(), dir.path().join("src")] {
+            let ignores = Ignores::root(&root);
+            assert!(ignores.is_ignored(&root.join("secret.rs"), false));
+            assert!(!ignores.is_ignored(&root.join("main.rs"), false));
+        }
+    }
+
+    #[test]
+    fn ignores_is_excluded_name() {
+        for name in DEFAULT_EXCLUDES {
+            let path = Path::new("project").join(name);
+            assert!(is_excluded_name(&path, &DEFAULT_EXCLUDES), "{}", name);
+        }
+
+        assert!(!is_excluded_name(
+            Path::new("src/target.rs"),
+            &DEFAULT_EXCLUDES
+        ));
+        assert!(!is_excluded_name(Path::new("my.git"), &DEFAULT_EXCLUDES));
+        assert!(!is_excluded_name(Path::new("target"), &["build"]));
+    }
+
+    #[test]
+    fn ignores_globs() {
+        let root = Path::new("tests/data");
+        let globs = |include: &[&str], exclude: &[&str]| {
+            let include = include.iter().map(|g| g.to_string()).collect::<Vec<_>>();
+            let exclude = exclude.iter().map(|g| g.to_string()).collect::<Vec<_>>();
+            Globs::new(root, &include, &exclude).unwrap()
+        };
+
+        let globs = globs(&["*.rs", "docs/"], &["docs/**", "!docs/keep.md", "gen_*"]);
+        assert!(!globs.is_excluded(&root.join("src/main.rs"), false));
+        assert!(!globs.is_excluded(&root.join("src"), true));
+        assert!(globs.is_excluded(&root.join("data.py"), false));
+        assert!(globs.is_excluded(&root.join("src/gen_parser.rs"), false));
+        assert!(globs.is_excluded(&root.join("docs/guide.md"), false));
+        assert!(!globs.is_excluded(&root.join("docs/keep.md"), false));
+    }
+
+    #[test]
+    fn ignores_globs_invalid() {
+        let exclude = vec!["src/{a".to_string()];
+        let e = Globs::new(Path::new("."), &[], &exclude).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+    }
+}
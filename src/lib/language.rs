@@ -0,0 +1,389 @@
+use std::path::Path;
+
+use super::compression;
+
+/// A language xloc knows how to recognise by file name.
+#[derive(Debug)]
+pub struct Language {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    filenames: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comments: &'static [(&'static str, &'static str)],
+}
+
+/// What a line of source code holds.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LineKind {
+    Code,
+    Comment,
+    Blank,
+}
+
+/// The name used for files that don't match any known language.
+pub const OTHER: &str = "Other";
+
+static LANGUAGES: &[Language] = &[
+    Language {
+        name: "C",
+        extensions: &["c", "h"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "C#",
+        extensions: &["cs"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "C++",
+        extensions: &["cc", "cpp", "cxx", "hh", "hpp", "hxx"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "CSS",
+        extensions: &["css", "scss", "sass", "less"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "Dockerfile",
+        extensions: &["dockerfile"],
+        filenames: &["Dockerfile"],
+        line_comments: &["#"],
+        block_comments: &[],
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "HTML",
+        extensions: &["html", "htm"],
+        filenames: &[],
+        line_comments: &[],
+        block_comments: &[("<!--", "-->")],
+    },
+    Language {
+        name: "Java",
+        extensions: &["java"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["js", "jsx", "mjs", "cjs"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "JSON",
+        extensions: &["json"],
+        filenames: &[],
+        line_comments: &[],
+        block_comments: &[],
+    },
+    Language {
+        name: "Kotlin",
+        extensions: &["kt", "kts"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "Log",
+        extensions: &["log"],
+        filenames: &[],
+        line_comments: &[],
+        block_comments: &[],
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        filenames: &[],
+        line_comments: &["--"],
+        block_comments: &[("--[[", "]]")],
+    },
+    Language {
+        name: "Makefile",
+        extensions: &["mk"],
+        filenames: &["Makefile", "makefile", "GNUmakefile"],
+        line_comments: &["#"],
+        block_comments: &[],
+    },
+    Language {
+        name: "Markdown",
+        extensions: &["md", "markdown"],
+        filenames: &[],
+        line_comments: &[],
+        block_comments: &[("<!--", "-->")],
+    },
+    Language {
+        name: "PHP",
+        extensions: &["php"],
+        filenames: &[],
+        line_comments: &["//", "#"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "Perl",
+        extensions: &["pl", "pm"],
+        filenames: &[],
+        line_comments: &["#"],
+        block_comments: &[],
+    },
+    Language {
+        name: "Python",
+        extensions: &["py", "pyi", "pyw"],
+        filenames: &[],
+        line_comments: &["#"],
+        block_comments: &[],
+    },
+    Language {
+        name: "Ruby",
+        extensions: &["rb"],
+        filenames: &["Gemfile", "Rakefile"],
+        line_comments: &["#"],
+        block_comments: &[],
+    },
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "SQL",
+        extensions: &["sql"],
+        filenames: &[],
+        line_comments: &["--"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh"],
+        filenames: &[],
+        line_comments: &["#"],
+        block_comments: &[],
+    },
+    Language {
+        name: "Swift",
+        extensions: &["swift"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "TOML",
+        extensions: &["toml"],
+        filenames: &["Cargo.lock"],
+        line_comments: &["#"],
+        block_comments: &[],
+    },
+    Language {
+        name: "Text",
+        extensions: &["txt"],
+        filenames: &[],
+        line_comments: &[],
+        block_comments: &[],
+    },
+    Language {
+        name: "TypeScript",
+        extensions: &["ts", "tsx"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+    },
+    Language {
+        name: "XML",
+        extensions: &["xml", "svg"],
+        filenames: &[],
+        line_comments: &[],
+        block_comments: &[("<!--", "-->")],
+    },
+    Language {
+        name: "YAML",
+        extensions: &["yaml", "yml"],
+        filenames: &[],
+        line_comments: &["#"],
+        block_comments: &[],
+    },
+];
+
+impl Language {
+    /// Classifies each line of `text`. A line with any code outside of
+    /// comments is code, and whitespace-only lines are blank even inside
+    /// a block comment. String literals aren't parsed, so comment
+    /// markers inside them are taken at face value.
+    pub fn classify(&self, text: &str) -> Vec<LineKind> {
+        let mut block = None;
+
+        text.lines()
+            .map(|line| self.classify_line(line, &mut block))
+            .collect()
+    }
+
+    /// Classifies a single line, given the end marker of the block
+    /// comment it starts in, if any, which is updated for the next line.
+    fn classify_line(&self, line: &str, block: &mut Option<&'static str>) -> LineKind {
+        let mut rest = line.trim();
+        let mut code = false;
+        let mut comment = false;
+
+        if rest.is_empty() {
+            return LineKind::Blank;
+        }
+
+        loop {
+            if let Some(end) = *block {
+                comment = true;
+
+                match rest.find(end) {
+                    Some(i) => {
+                        rest = rest[i + end.len()..].trim_start();
+                        *block = None;
+                    }
+                    None => break,
+                }
+            }
+
+            if rest.is_empty() {
+                break;
+            }
+
+            // Block markers come first, since Lua's `--[[` starts with `--`
+            if let Some((start, end)) = self
+                .block_comments
+                .iter()
+                .find(|(s, _)| rest.starts_with(s))
+            {
+                rest = &rest[start.len()..];
+                *block = Some(end);
+                continue;
+            }
+
+            if self.line_comments.iter().any(|c| rest.starts_with(c)) {
+                comment = true;
+                break;
+            }
+
+            code = true;
+
+            // Skip ahead to the next comment marker, if there is one
+            let starts = self.block_comments.iter().map(|(s, _)| s);
+            let next = self
+                .line_comments
+                .iter()
+                .chain(starts)
+                .filter_map(|marker| rest.find(marker))
+                .min();
+
+            match next {
+                Some(i) if i > 0 => rest = &rest[i..],
+                _ => break,
+            }
+        }
+
+        match (code, comment) {
+            (true, _) => LineKind::Code,
+            (false, true) => LineKind::Comment,
+            (false, false) => LineKind::Blank,
+        }
+    }
+}
+
+/// Classifies each line of a file's `text` by its language. Every
+/// non-blank line of a file in an unknown language is code.
+pub fn classify(path: &Path, text: &str) -> Vec<LineKind> {
+    match detect(path) {
+        Some(lang) => lang.classify(text),
+        None => text
+            .lines()
+            .map(|line| match line.trim().is_empty() {
+                true => LineKind::Blank,
+                false => LineKind::Code,
+            })
+            .collect(),
+    }
+}
+
+/// Finds the [Language] of a file from its name or extension. For
+/// compressed files, the language of the file inside is used.
+pub fn detect(path: &Path) -> Option<&'static Language> {
+    let path = compression::inner_path(path);
+    let filename = path.file_name()?.to_str()?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    LANGUAGES.iter().find(|lang| {
+        lang.filenames.contains(&filename)
+            || extension
+                .as_deref()
+                .is_some_and(|ext| lang.extensions.contains(&ext))
+    })
+}
+
+/// Gets the display name of a file's language, or [OTHER].
+pub fn name_of(path: &Path) -> &'static str {
+    detect(path).map_or(OTHER, |lang| lang.name)
+}
+
+#[cfg(test)]
+mod language_tests {
+    use std::path::Path;
+
+    use super::{classify, detect, name_of, LineKind, OTHER};
+
+    #[test]
+    fn language_detect_extension() {
+        assert_eq!(name_of(Path::new("tests/data/data.rs")), "Rust");
+        assert_eq!(name_of(Path::new("tests/data/data.py")), "Python");
+        assert_eq!(name_of(Path::new("README.MD")), "Markdown");
+        assert_eq!(name_of(Path::new("logs/access.log.gz")), "Log");
+    }
+
+    #[test]
+    fn language_detect_filename() {
+        assert_eq!(name_of(Path::new("docker/Dockerfile")), "Dockerfile");
+        assert_eq!(name_of(Path::new("Makefile")), "Makefile");
+    }
+
+    #[test]
+    fn language_detect_unknown() {
+        assert!(detect(Path::new("image.png")).is_none());
+        assert_eq!(name_of(Path::new("LICENSE")), OTHER);
+    }
+
+    #[test]
+    fn language_classify() {
+        use LineKind::*;
+
+        let rust = "// hi\nfn main() { /* a */ }\n\n/* one\n\n two */\nlet x = 1; // x\n";
+        assert_eq!(
+            classify(Path::new("main.rs"), rust),
+            vec![Comment, Code, Blank, Comment, Blank, Comment, Code]
+        );
+
+        let lua = "--[[ a\nb ]] x = 1\n-- c\n";
+        assert_eq!(
+            classify(Path::new("init.lua"), lua),
+            vec![Comment, Code, Comment]
+        );
+
+        assert_eq!(classify(Path::new("LICENSE"), "# a\n\n"), vec![Code, Blank]);
+    }
+}
@@ -1,9 +1,9 @@
 //! # xloc
 //!
 //! - A fast, multi-threaded line counting utility. xloc hopes to speed up
-//! in places where other tools slow down.
+//!   in places where other tools slow down.
 //! - An easy to use API is available through `xloc::App` if you would
-//! like to count lines/words from within another Rust project.
+//!   like to count lines/words from within another Rust project.
 //! - Simple and intuitive command line interface.
 //!
 //! ---
@@ -28,6 +28,70 @@
 //!
 //! ## Count lines for all files in the src dir, with 6 jobs.
 //! xloc -j 6 src
+//!
+//! ## Count ignored files too, like build outputs listed in .gitignore.
+//! xloc --no-ignore .
+//!
+//! ## Only count Rust files, leaving out the docs.
+//! xloc --include '*.rs' --exclude 'docs/**' .
+//!
+//! ## List the directories excluded by default, like .git and target.
+//! xloc --list-default-excludes
+//!
+//! ## Count overlapping paths once, with a subtotal for each path.
+//! xloc --per-root src src/lib tests
+//!
+//! ## Count a home directory without wandering into mounted filesystems.
+//! xloc --one-file-system ~
+//!
+//! ## Count the words piped in on stdin, like wc.
+//! cat notes.txt | xloc -w
+//!
+//! ## Count exactly the files in a list, one per line or NUL separated.
+//! git ls-files | xloc --files-from -
+//!
+//! ## Count only the files tracked by git, from the repository index.
+//! xloc --git-tracked .
+//!
+//! ## Count a tagged release straight from git, without checking it out.
+//! xloc --rev v1.2.0 .
+//!
+//! ## Count hidden files like .env too, but only two directories deep.
+//! xloc --hidden --max-depth 2 .
+//!
+//! ## Skip stray logs and dumps over 10 MiB, listing them as skipped.
+//! xloc --max-file-size 10M --show-skipped .
+//!
+//! ## Count the files touched in the last 30 days, or during 2023.
+//! xloc --newer-than 30d .
+//! xloc --newer-than 2023-01-01 --older-than 2024-01-01 .
+//!
+//! ## Follow symlinks that stay inside the counted dir, skipping loops.
+//! xloc --symlinks within-root .
+//!
+//! ## Count Unicode words (UAX #29) in translated strings.
+//! xloc -w --word-mode unicode locales
+//!
+//! ## Count the decompressed contents of .gz, .bz2, .xz and .zst files.
+//! xloc --decompress logs
+//!
+//! ## Count the files inside .tar, .tar.gz, .tgz and .zip archives.
+//! xloc --archives --per-file release.tar.gz
+//!
+//! ## Show the code, comment and blank lines added since a release.
+//! xloc diff v1.2.0 HEAD
+//!
+//! ## Show the lines staged for the next commit, per language.
+//! xloc diff --staged
+//!
+//! ## Chart the lines of each language at every tag, as CSV.
+//! xloc history --sample tag > history.csv
+//!
+//! ## Show who last changed the lines in each language, via git blame.
+//! xloc --blame --mailmap src
+//!
+//! ## Report trailing whitespace and indentation style per file/language.
+//! xloc --hygiene src
 //! ```
 //!
 //! ## Getting started in your own project
@@ -35,7 +99,30 @@
 //! Check out our documentation on `xloc::App` below.
 
 mod app;
+mod archive;
+mod blame;
+mod compression;
 mod counter;
+mod diff;
+mod encoding;
+mod git;
+mod history;
+mod hygiene;
+mod ignores;
+mod language;
+mod source;
+mod stream;
+mod summary;
+mod symlinks;
 mod threads;
+mod words;
 
 pub use app::App;
+pub use blame::Authorship;
+pub use diff::{Change, Delta, DiffReport};
+pub use history::{History, Point, Sampling};
+pub use hygiene::{Hygiene, HygieneReport};
+pub use ignores::DEFAULT_EXCLUDES;
+pub use summary::{SkipReason, Skipped, Summary};
+pub use symlinks::SymlinkPolicy;
+pub use words::WordMode;
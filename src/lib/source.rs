@@ -0,0 +1,107 @@
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Result, Seek};
+use std::path::Path;
+use std::sync::Arc;
+
+use git2::{Blob, Repository};
+
+use super::git::Snapshot;
+
+/// The contents of a file being counted. Archives need to seek in them,
+/// so they are more than a [Read].
+pub trait Content: Read + Seek {}
+
+impl<T: Read + Seek> Content for T {}
+
+/// Where the contents of the files being counted are read from.
+#[derive(Debug, Clone, Default)]
+pub enum Source {
+    /// Files are read from the filesystem.
+    #[default]
+    Filesystem,
+
+    /// Files are read as blobs from a revision of a git repository.
+    Revision(Arc<Snapshot>),
+}
+
+impl Source {
+    /// Opens the source to read files from, once for each thread.
+    pub fn open(&self) -> Result<Reader<'_>> {
+        match self {
+            Self::Filesystem => Ok(Reader::Filesystem),
+            Self::Revision(snapshot) => Ok(Reader::Revision(snapshot, snapshot.open()?)),
+        }
+    }
+}
+
+/// An open [Source].
+pub enum Reader<'a> {
+    Filesystem,
+    Revision(&'a Snapshot, Repository),
+}
+
+impl Reader<'_> {
+    /// Gets the contents of a file.
+    pub fn read(&self, file: &Path) -> Result<Box<dyn Content + '_>> {
+        match self {
+            Self::Filesystem => Ok(Box::new(File::open(file)?)),
+            Self::Revision(snapshot, repo) => {
+                let blob = snapshot.blob(repo, file)?;
+                Ok(Box::new(Cursor::new(BlobContent(blob))))
+            }
+        }
+    }
+
+    /// Gets the size of a file in bytes, without reading it.
+    pub fn size(&self, file: &Path) -> Result<u64> {
+        match self {
+            Self::Filesystem => Ok(fs::metadata(file)?.len()),
+            Self::Revision(snapshot, repo) => snapshot.size(repo, file),
+        }
+    }
+}
+
+/// The contents of a blob, read in place rather than copied out.
+struct BlobContent<'r>(Blob<'r>);
+
+impl AsRef<[u8]> for BlobContent<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.0.content()
+    }
+}
+
+#[cfg(test)]
+mod source_tests {
+    use std::io::{ErrorKind, Read};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use super::Source;
+    use crate::git::git_tests::commit;
+    use crate::git::Snapshot;
+
+    fn read(source: &Source, file: &Path) -> std::io::Result<String> {
+        let mut contents = String::new();
+        source.open()?.read(file)?.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+
+    #[test]
+    fn source_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("main.rs");
+        commit(dir.path(), &[("main.rs", "fn main() {}\n")], "init");
+        std::fs::write(&main, "\n").unwrap();
+
+        assert_eq!(read(&Source::Filesystem, &main).unwrap(), "\n");
+
+        let snapshot = Snapshot::new(dir.path(), "HEAD").unwrap();
+        let source = Source::Revision(Arc::new(snapshot));
+        assert_eq!(read(&source, &main).unwrap(), "fn main() {}\n");
+        assert_eq!(source.open().unwrap().size(&main).unwrap(), 13);
+        assert_eq!(Source::Filesystem.open().unwrap().size(&main).unwrap(), 1);
+
+        let e = read(&source, &dir.path().join("lib.rs")).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,230 @@
+use std::io::{Error, ErrorKind, Read, Result};
+
+use encoding_rs::{CoderResult, Encoding};
+
+use super::encoding::{self, SNIFF_LEN};
+
+/// How many bytes are read from the underlying reader at a time.
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// A reader over a file's contents that sniffs the encoding and binary
+/// status from the first block, then streams the rest in fixed size
+/// chunks so memory stays bounded regardless of the file size.
+pub struct Stream<R> {
+    inner: R,
+    head: Vec<u8>,
+    encoding: &'static Encoding,
+    bom: usize,
+}
+
+impl<R: Read> Stream<R> {
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        (&mut inner).take(SNIFF_LEN as u64).read_to_end(&mut head)?;
+        let (encoding, bom) = encoding::sniff(&head);
+
+        Ok(Self {
+            inner,
+            head,
+            encoding,
+            bom,
+        })
+    }
+
+    /// Whether the first block of the stream looks like binary data.
+    pub fn is_binary(&self) -> bool {
+        encoding::is_binary(&self.head)
+    }
+
+    /// Whether lines can be counted on the raw bytes of this stream.
+    pub fn is_wide(&self) -> bool {
+        encoding::is_wide(self.encoding)
+    }
+
+    /// Counts the `\n` bytes in the stream, without decoding it.
+    pub fn count_newlines(mut self) -> Result<usize> {
+        let mut total = bytecount(&self.head);
+        let mut buf = vec![0; CHUNK_LEN];
+
+        loop {
+            match self.inner.read(&mut buf) {
+                Ok(0) => return Ok(total),
+                Ok(n) => total += bytecount(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Reads the whole stream as raw bytes, byte order mark included,
+    /// without decoding it.
+    pub fn into_bytes(mut self) -> Result<Vec<u8>> {
+        self.inner.read_to_end(&mut self.head)?;
+        Ok(self.head)
+    }
+
+    /// Decodes the stream, calling `f` with each decoded chunk of text.
+    /// Chunks end with a newline where possible, so lines and words are
+    /// not split between them. A line longer than a chunk is split after
+    /// its last space or tab instead, or anywhere if it has none, so a
+    /// file without line breaks is still read in bounded pieces.
+    ///
+    /// Fails with [ErrorKind::InvalidData] if the stream has a byte order
+    /// mark but is not valid in the encoding it declares.
+    pub fn for_each_text<F: FnMut(&str)>(mut self, mut f: F) -> Result<()> {
+        let mut decoder = self.encoding.new_decoder_without_bom_handling();
+        let mut pending = self.head.split_off(self.bom);
+        let mut buf = vec![0; CHUNK_LEN];
+        let mut text = String::new();
+        let mut malformed = false;
+
+        loop {
+            let n = match self.inner.read(&mut buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            pending.extend_from_slice(&buf[..n]);
+            let last = n == 0;
+            let mut input = &pending[..];
+
+            loop {
+                let wanted = decoder
+                    .max_utf8_buffer_length(input.len())
+                    .unwrap_or(CHUNK_LEN);
+                text.reserve(wanted);
+
+                let (result, read, replaced) = decoder.decode_to_string(input, &mut text, last);
+                malformed |= replaced;
+                input = &input[read..];
+
+                if let CoderResult::InputEmpty = result {
+                    break;
+                }
+            }
+
+            pending.clear();
+
+            if last {
+                f(&text);
+                break;
+            }
+
+            let end = match text.rfind('\n') {
+                Some(end) => Some(end + 1),
+                None if text.len() >= CHUNK_LEN => text
+                    .rfind([' ', '\t'])
+                    .map(|end| end + 1)
+                    .or(Some(text.len())),
+                None => None,
+            };
+
+            if let Some(end) = end {
+                f(&text[..end]);
+                text.drain(..end);
+            }
+        }
+
+        if malformed && self.bom > 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "stream is not valid in the encoding of its byte order mark",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn bytecount(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|b| **b == b'\n').count()
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use std::io::ErrorKind;
+
+    use super::{Stream, CHUNK_LEN};
+
+    #[test]
+    fn stream_count_newlines() {
+        let data = "line\n".repeat(20_000);
+        let stream = Stream::new(data.as_bytes()).unwrap();
+        assert!(!stream.is_binary());
+        assert_eq!(stream.count_newlines().unwrap(), 20_000);
+    }
+
+    #[test]
+    fn stream_for_each_text_whole_lines() {
+        let data = "héllo wörld\n".repeat(20_000);
+        let stream = Stream::new(data.as_bytes()).unwrap();
+        let mut chunks = 0;
+        let mut total = String::new();
+
+        stream
+            .for_each_text(|text| {
+                chunks += 1;
+                assert!(text.is_empty() || text.ends_with('\n'));
+                total.push_str(text);
+            })
+            .unwrap();
+
+        assert!(chunks > 1);
+        assert_eq!(total, data);
+    }
+
+    #[test]
+    fn stream_for_each_text_no_newlines() {
+        let data = "word ".repeat(100_000);
+        let stream = Stream::new(data.as_bytes()).unwrap();
+        let mut chunks = 0;
+        let mut total = String::new();
+
+        stream
+            .for_each_text(|text| {
+                chunks += 1;
+                assert!(text.len() <= 2 * CHUNK_LEN);
+                assert!(text.is_empty() || text.ends_with(' '));
+                total.push_str(text);
+            })
+            .unwrap();
+
+        assert!(chunks > 1);
+        assert_eq!(total, data);
+
+        // Without any whitespace to split at, chunks still stay bounded
+        let data = "x".repeat(4 * CHUNK_LEN);
+        let mut longest = 0;
+        let mut total = 0;
+
+        Stream::new(data.as_bytes())
+            .unwrap()
+            .for_each_text(|text| {
+                longest = longest.max(text.len());
+                total += text.len();
+            })
+            .unwrap();
+
+        assert!(longest <= 2 * CHUNK_LEN);
+        assert_eq!(total, data.len());
+    }
+
+    #[test]
+    fn stream_for_each_text_utf16() {
+        let data = b"\xFF\xFEh\0i\0\n\0";
+        let stream = Stream::new(&data[..]).unwrap();
+        assert!(stream.is_wide());
+
+        let mut total = String::new();
+        stream.for_each_text(|text| total.push_str(text)).unwrap();
+        assert_eq!(total, "hi\n");
+    }
+
+    #[test]
+    fn stream_for_each_text_malformed() {
+        let stream = Stream::new(&b"\xEF\xBB\xBFcaf\xE9\n"[..]).unwrap();
+        let e = stream.for_each_text(|_| ()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+/// Why a file was left out of a count.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum SkipReason {
+    /// The file or directory could not be opened due to its permissions.
+    PermissionDenied,
+
+    /// The file has a byte order mark, but its contents are not valid
+    /// in that encoding.
+    InvalidEncoding,
+
+    /// The file looks like binary data, see [crate::App::set_binary].
+    Binary,
+
+    /// The file is compressed, but could not be decompressed.
+    Corrupt,
+
+    /// The file was found while scanning, but was gone when it was read.
+    Vanished,
+
+    /// The file is a symlink to something that doesn't exist.
+    BrokenLink,
+
+    /// The directory is a symlink back to a directory that contains it,
+    /// see [crate::App::set_symlinks].
+    SymlinkLoop,
+
+    /// The file could not be read for any other reason.
+    Unreadable,
+
+    /// The file is larger than the limit, see [crate::App::set_max_file_size].
+    TooLarge,
+}
+
+impl From<&Error> for SkipReason {
+    fn from(e: &Error) -> Self {
+        match e.kind() {
+            ErrorKind::PermissionDenied => Self::PermissionDenied,
+            ErrorKind::NotFound => Self::Vanished,
+            ErrorKind::InvalidData => Self::InvalidEncoding,
+            _ => Self::Unreadable,
+        }
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            Self::PermissionDenied => "permission denied",
+            Self::InvalidEncoding => "invalid encoding",
+            Self::Binary => "binary",
+            Self::Corrupt => "corrupt compressed data",
+            Self::Vanished => "vanished during scan",
+            Self::BrokenLink => "broken symlink",
+            Self::SymlinkLoop => "symlink loop",
+            Self::Unreadable => "unreadable",
+            Self::TooLarge => "too large",
+        };
+
+        write!(f, "{}", reason)
+    }
+}
+
+/// A file that was left out of a count, and why.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Skipped {
+    /// The path of the skipped file or directory.
+    pub path: PathBuf,
+
+    /// Why it was skipped.
+    pub reason: SkipReason,
+}
+
+impl Skipped {
+    pub fn new(path: PathBuf, reason: SkipReason) -> Self {
+        Self { path, reason }
+    }
+}
+
+/// The result of counting lines or words across a set of files.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Summary {
+    /// The total line or word count.
+    pub count: usize,
+
+    /// The number of files that were counted.
+    pub files: usize,
+
+    /// The line or word count of each file that was counted. Files inside
+    /// archives are named like `release.tar!/src/main.rs`.
+    pub per_file: BTreeMap<PathBuf, usize>,
+
+    /// The line or word count of each path that was counted, in the
+    /// order they were given, see [crate::App::summarize_all].
+    pub per_root: Vec<(PathBuf, usize)>,
+
+    /// Every file or directory that was skipped, sorted by path once
+    /// the summary is finished, see [Summary::finish].
+    pub skipped: Vec<Skipped>,
+}
+
+impl Summary {
+    /// Adds another summary into this one. Its skipped files are added
+    /// to the end, until the summary is finished.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xloc::{SkipReason, Skipped, Summary};
+    ///
+    /// let mut summary = Summary::default();
+    ///
+    /// summary.add(&Summary {
+    ///     count: 10,
+    ///     files: 2,
+    ///     skipped: vec![Skipped::new("a.png".into(), SkipReason::Binary)],
+    ///     ..Summary::default()
+    /// });
+    ///
+    /// assert_eq!(summary.count, 10);
+    /// assert_eq!(summary.skipped.len(), 1);
+    /// ```
+    pub fn add(&mut self, other: &Self) {
+        self.count += other.count;
+        self.files += other.files;
+        self.per_file.extend(other.per_file.clone());
+        self.per_root.extend(other.per_root.iter().cloned());
+        self.skipped.extend(other.skipped.iter().cloned());
+    }
+
+    /// Sorts the skipped files by path, once every summary has been added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use xloc::{SkipReason, Skipped, Summary};
+    ///
+    /// let mut summary = Summary::default();
+    ///
+    /// for path in ["b.png", "a.png"] {
+    ///     summary.add(&Summary {
+    ///         skipped: vec![Skipped::new(path.into(), SkipReason::Binary)],
+    ///         ..Summary::default()
+    ///     });
+    /// }
+    ///
+    /// summary.finish();
+    /// assert_eq!(summary.skipped[0].path, Path::new("a.png"));
+    /// ```
+    pub fn finish(&mut self) {
+        self.skipped.sort();
+    }
+
+    /// Gets how many files were skipped for the given reason.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xloc::{SkipReason, Skipped, Summary};
+    ///
+    /// let summary = Summary {
+    ///     skipped: vec![Skipped::new("a.png".into(), SkipReason::Binary)],
+    ///     ..Summary::default()
+    /// };
+    ///
+    /// assert_eq!(summary.skipped_for(SkipReason::Binary), 1);
+    /// assert_eq!(summary.skipped_for(SkipReason::Vanished), 0);
+    /// ```
+    pub fn skipped_for(&self, reason: SkipReason) -> usize {
+        self.skipped.iter().filter(|s| s.reason == reason).count()
+    }
+}
+
+#[cfg(test)]
+mod summary_tests {
+    use std::io::{Error, ErrorKind};
+
+    use super::{SkipReason, Skipped, Summary};
+
+    #[test]
+    fn summary_skip_reason_from_error() {
+        let reason = |kind| SkipReason::from(&Error::new(kind, "oops"));
+
+        assert_eq!(
+            reason(ErrorKind::PermissionDenied),
+            SkipReason::PermissionDenied
+        );
+        assert_eq!(reason(ErrorKind::NotFound), SkipReason::Vanished);
+        assert_eq!(reason(ErrorKind::Interrupted), SkipReason::Unreadable);
+    }
+
+    #[test]
+    fn summary_finish_sorts_skipped() {
+        let mut summary = Summary::default();
+        let skipped = |path: &str, reason| Summary {
+            count: 1,
+            files: 1,
+            skipped: vec![Skipped::new(path.into(), reason)],
+            ..Summary::default()
+        };
+
+        summary.add(&skipped("b", SkipReason::Binary));
+        summary.add(&skipped("a", SkipReason::Vanished));
+        summary.finish();
+
+        assert_eq!(summary.count, 2);
+        assert_eq!(summary.skipped[0].path.to_str(), Some("a"));
+        assert_eq!(summary.skipped[1].reason, SkipReason::Binary);
+    }
+}
@@ -0,0 +1,113 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Which symbolic links are followed while scanning a directory. Paths
+/// passed to xloc directly are always followed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SymlinkPolicy {
+    /// Symlinks are left out, so every file is counted once through
+    /// its real path.
+    Never,
+
+    /// Symlinks are followed if they point somewhere inside the path
+    /// being counted.
+    WithinRoot,
+
+    /// Every symlink is followed, wherever it points. Symlinks back to
+    /// a directory being scanned are still skipped.
+    Always,
+}
+
+impl Default for SymlinkPolicy {
+    /// ```
+    /// use xloc::SymlinkPolicy;
+    ///
+    /// assert_eq!(SymlinkPolicy::default(), SymlinkPolicy::Always);
+    /// ```
+    fn default() -> Self {
+        Self::Always
+    }
+}
+
+impl FromStr for SymlinkPolicy {
+    type Err = Error;
+
+    /// ```
+    /// use xloc::SymlinkPolicy;
+    ///
+    /// assert_eq!("within-root".parse::<SymlinkPolicy>().unwrap(), SymlinkPolicy::WithinRoot);
+    /// assert!("sometimes".parse::<SymlinkPolicy>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "never" => Ok(Self::Never),
+            "within-root" => Ok(Self::WithinRoot),
+            "always" => Ok(Self::Always),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown symlink policy: {}", s),
+            )),
+        }
+    }
+}
+
+/// Identifies a file or directory regardless of the path it was reached
+/// by, so a symlink back to a directory being scanned is detected as a
+/// cycle, and hard links to a file are only counted once.
+#[cfg(unix)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct FileId(u64, u64);
+
+#[cfg(not(unix))]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct FileId(PathBuf);
+
+impl FileId {
+    /// Gets the id of the file or directory at `path`, following symlinks.
+    #[cfg(unix)]
+    pub fn of(path: &Path) -> Result<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        let meta = fs::metadata(path)?;
+        Ok(Self(meta.dev(), meta.ino()))
+    }
+
+    /// Gets the id of the file or directory at `path`, following symlinks.
+    #[cfg(not(unix))]
+    pub fn of(path: &Path) -> Result<Self> {
+        Ok(Self(fs::canonicalize(path)?))
+    }
+}
+
+/// Gets the id of the device `path` is on, following symlinks, on
+/// platforms that have one.
+#[cfg(unix)]
+pub fn device_of(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::metadata(path).ok().map(|m| m.dev())
+}
+
+/// Gets the id of the device `path` is on, following symlinks, on
+/// platforms that have one.
+#[cfg(not(unix))]
+pub fn device_of(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Whether `path` is itself a symlink, rather than what it points to.
+pub fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|m| m.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
+/// Whether the symlink at `path` resolves to something inside `root`,
+/// which must already be canonical.
+pub fn is_within(path: &Path, root: &Path) -> bool {
+    fs::canonicalize(path)
+        .map(|target: PathBuf| target.starts_with(root))
+        .unwrap_or(false)
+}
@@ -1,55 +1,404 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Read};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
-use std::{fs, thread};
+use std::thread;
 
-use regex::Regex;
+use super::archive::{Archive, EntryRules};
+use super::blame::{self, Authorship};
+use super::compression::Compression;
+use super::diff::{Delta, Side};
+use super::hygiene::{Hygiene, HygieneReport, Scanner};
+use super::language;
+use super::source::{Reader, Source};
+use super::stream::Stream;
+use super::summary::{SkipReason, Skipped, Summary};
+use super::words::Metric;
 
-pub fn handle_in_thread(tx: mpsc::Sender<usize>, files: Vec<PathBuf>, words: bool) {
+/// Options that control how each worker reads its files.
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    pub binary: bool,
+    pub decompress: bool,
+    pub archives: bool,
+    pub rules: EntryRules,
+    pub source: Source,
+    pub max_file_size: Option<u64>,
+}
+
+impl ReadOptions {
+    /// The compression of a file named `path`, if it is decompressed
+    /// before it is read.
+    pub fn compression(&self, path: &Path) -> Option<Compression> {
+        if self.decompress {
+            Compression::detect(path)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn handle_in_thread<T, F>(tx: mpsc::Sender<T>, files: Vec<PathBuf>, work: F)
+where
+    T: Send + 'static,
+    F: FnOnce(Vec<PathBuf>) -> T + Send + 'static,
+{
     thread::spawn(move || {
-        tx.send(handle(files, words)).unwrap();
+        tx.send(work(files)).unwrap();
+    });
+}
+
+pub fn handle(files: Vec<PathBuf>, metric: &Metric, options: &ReadOptions) -> Summary {
+    let mut result = Summary::default();
+
+    visit(
+        files,
+        options,
+        |stream| metric.count_stream(stream),
+        |file, counted| record(&mut result, file, counted),
+    );
+
+    result
+}
+
+/// Counts the contents of any reader, like [handle] does for a file.
+/// The `name` is used to report it, and to detect its compression.
+pub fn handle_reader<R: Read>(
+    reader: R,
+    name: PathBuf,
+    metric: &Metric,
+    options: &ReadOptions,
+) -> Summary {
+    let mut result = Summary::default();
+    let counted = read_reader(reader, &name, options, |stream| metric.count_stream(stream));
+    record(&mut result, name, counted);
+    result
+}
+
+fn record(result: &mut Summary, file: PathBuf, counted: Result<usize, SkipReason>) {
+    match counted {
+        Ok(count) => {
+            result.count += count;
+            result.files += 1;
+            result.per_file.insert(file, count);
+        }
+        Err(reason) => result.skipped.push(Skipped::new(file, reason)),
+    }
+}
+
+pub fn handle_hygiene(files: Vec<PathBuf>, options: &ReadOptions) -> HygieneReport {
+    let mut report = HygieneReport::default();
+
+    visit(files, options, scan_hygiene, |file, scanned| {
+        record_hygiene(&mut report, file, scanned)
+    });
+
+    report
+}
+
+/// Builds the hygiene report of any reader, like [handle_hygiene] does
+/// for a file. The `name` is used to report it, and to detect its
+/// compression and language.
+pub fn handle_hygiene_reader<R: Read>(
+    reader: R,
+    name: PathBuf,
+    options: &ReadOptions,
+) -> HygieneReport {
+    let mut report = HygieneReport::default();
+    let scanned = read_reader(reader, &name, options, scan_hygiene);
+    record_hygiene(&mut report, name, scanned);
+    report
+}
+
+fn record_hygiene(report: &mut HygieneReport, file: PathBuf, scanned: Result<Hygiene, SkipReason>) {
+    match scanned {
+        Ok(hygiene) => {
+            let language = language::name_of(&file);
+            report.insert(file, language, hygiene);
+        }
+        Err(reason) => report.skipped.push(Skipped::new(file, reason)),
+    }
+}
+
+fn scan_hygiene(stream: Stream<Box<dyn Read + '_>>) -> io::Result<Hygiene> {
+    let mut scanner = Scanner::new();
+    stream.for_each_text(|text| scanner.scan(text))?;
+    Ok(scanner.finish())
+}
+
+/// Attributes the lines of every file to their authors with git blame.
+pub fn handle_blame(files: Vec<PathBuf>, options: &ReadOptions, mailmap: bool) -> Authorship {
+    let mut authorship = Authorship::default();
+
+    visit(files, options, read_raw, |file, read| {
+        let blamed = read.map(|(content, text)| blame::blame(&file, &content, &text, mailmap));
+
+        match blamed {
+            Ok(Ok(authors)) => authorship.insert(language::name_of(&file), authors),
+            Ok(Err(e)) => authorship
+                .skipped
+                .push(Skipped::new(file, SkipReason::from(&e))),
+            Err(reason) => authorship.skipped.push(Skipped::new(file, reason)),
+        }
     });
+
+    authorship
 }
 
-pub fn handle(files: Vec<PathBuf>, words: bool) -> usize {
-    let mut result = 0;
-    let pattern;
+/// Compares the files of two sides of a diff, matched up by their
+/// name within each side. Only one pair of files is read at a time, or
+/// one pair of archives if they are expanded. Files that are skipped on
+/// either side are not compared.
+pub fn handle_diff(
+    names: Vec<PathBuf>,
+    old: &Side,
+    new: &Side,
+    options: &ReadOptions,
+) -> (Vec<(PathBuf, Delta)>, Vec<Skipped>) {
+    let mut deltas = vec![];
+    let mut skipped = vec![];
+
+    let readers = old.source.open().and_then(|o| Ok((o, new.source.open()?)));
+    let (old_reader, new_reader) = match readers {
+        Ok(readers) => readers,
+        Err(e) => {
+            let reason = SkipReason::from(&e);
+            let skipped = names.into_iter().map(|n| Skipped::new(n, reason));
+            return (deltas, skipped.collect());
+        }
+    };
+
+    for name in names {
+        // Archives are expanded into entries named within the side too
+        let mut pairs =
+            BTreeMap::<PathBuf, [Option<(PathBuf, Result<String, SkipReason>)>; 2]>::new();
+
+        for (i, side, reader) in [(0, old, &old_reader), (1, new, &new_reader)] {
+            if let Some(file) = side.files.get(&name) {
+                let options = ReadOptions {
+                    rules: side.rules.clone(),
+                    ..options.clone()
+                };
+
+                visit_file(
+                    reader,
+                    file.clone(),
+                    &options,
+                    &read_text,
+                    &mut |file, read| {
+                        let name = file.strip_prefix(&side.root).unwrap_or(&file).to_owned();
+                        pairs.entry(name).or_default()[i] = Some((file, read));
+                    },
+                );
+            }
+        }
+
+        for (name, [old_read, new_read]) in pairs {
+            let text = |read: Option<(PathBuf, Result<String, SkipReason>)>| match read {
+                Some((file, read)) => read.map_err(|reason| Skipped::new(file, reason)),
+                None => Ok(String::new()),
+            };
 
-    if words {
-        pattern = r#"(\w?\\?['"]?\w+\\?['"]?(\w+)?)+"#;
-    } else {
-        pattern = r"\n";
+            let delta = text(old_read).and_then(|old| Ok((old, text(new_read)?)));
+
+            match delta.map(|(old, new)| Delta::between(&name, &old, &new)) {
+                Ok(Ok(delta)) => deltas.push((name, delta)),
+                Ok(Err(e)) => skipped.push(Skipped::new(name, SkipReason::from(&e))),
+                Err(file) => skipped.push(file),
+            }
+        }
     }
 
-    let rgx = Regex::new(pattern).unwrap();
+    (deltas, skipped)
+}
+
+/// Reads the whole text of any reader. The `name` is used to detect its
+/// compression.
+pub fn handle_text_reader<R: Read>(
+    reader: R,
+    name: &Path,
+    options: &ReadOptions,
+) -> Result<String, SkipReason> {
+    read_reader(reader, name, options, read_text)
+}
+
+/// Decodes a whole [Stream] into a string.
+fn read_text(stream: Stream<Box<dyn Read + '_>>) -> io::Result<String> {
+    let mut text = String::new();
+    stream.for_each_text(|chunk| text.push_str(chunk))?;
+    Ok(text)
+}
+
+/// Reads a file's raw bytes along with its decoded text, for work that
+/// needs the bytes as they are on disk.
+fn read_raw(stream: Stream<Box<dyn Read + '_>>) -> io::Result<(Vec<u8>, String)> {
+    let content = stream.into_bytes()?;
+    let mut text = String::new();
+    Stream::new(&content[..])?.for_each_text(|chunk| text.push_str(chunk))?;
+    Ok((content, text))
+}
+
+/// Reads every file from the [Source] as a [Stream] with `work`,
+/// expanding archives into their entries if requested, and passes each
+/// result to `record`.
+fn visit<T, W, R>(files: Vec<PathBuf>, options: &ReadOptions, work: W, mut record: R)
+where
+    W: Fn(Stream<Box<dyn Read + '_>>) -> io::Result<T>,
+    R: FnMut(PathBuf, Result<T, SkipReason>),
+{
+    let source = match options.source.open() {
+        Ok(source) => source,
+        Err(e) => {
+            for file in files {
+                record(file, Err(SkipReason::from(&e)));
+            }
+
+            return;
+        }
+    };
 
     for file in files {
-        if let Ok(s) = fs::read_to_string(file) {
-            result += rgx.find_iter(&s).count();
+        visit_file(&source, file, options, &work, &mut record);
+    }
+}
+
+/// Reads a single file from an open [Source] like [visit] does.
+fn visit_file<T, W, R>(
+    source: &Reader,
+    file: PathBuf,
+    options: &ReadOptions,
+    work: &W,
+    record: &mut R,
+) where
+    W: Fn(Stream<Box<dyn Read + '_>>) -> io::Result<T>,
+    R: FnMut(PathBuf, Result<T, SkipReason>),
+{
+    // Only the size is looked up, so huge files are never read
+    if let Some(max) = options.max_file_size {
+        match source.size(&file) {
+            Ok(size) if size > max => return record(file, Err(SkipReason::TooLarge)),
+            Ok(_) => (),
+            Err(e) => return record(file, Err(SkipReason::from(&e))),
         }
     }
 
-    result
+    let archive = match options.archives {
+        true => Archive::detect(&file),
+        false => None,
+    };
+
+    if let Some(archive) = archive {
+        let walked = source.read(&file).and_then(|content| {
+            archive.for_each_entry(content, &file, &options.rules, |entry, reader| {
+                let read = read_stream(reader, options.compression(&entry), true, options, work);
+                record(entry, read);
+            })
+        });
+
+        if let Err(e) = walked {
+            let reason = match e.kind() {
+                ErrorKind::NotFound | ErrorKind::PermissionDenied => SkipReason::from(&e),
+                _ => SkipReason::Corrupt,
+            };
+
+            record(file, Err(reason));
+        }
+
+        return;
+    }
+
+    let read = match source.read(&file) {
+        Ok(reader) => read_reader(reader, &file, options, work),
+        Err(e) => Err(SkipReason::from(&e)),
+    };
+
+    record(file, read);
+}
+
+/// Reads any reader like a file named `name`, which is decompressed if
+/// its name says it is compressed, and passes it to `work` unless it
+/// should be skipped. This is where every reader that isn't an archive
+/// entry is read.
+fn read_reader<'a, T, R, W>(
+    reader: R,
+    name: &Path,
+    options: &ReadOptions,
+    work: W,
+) -> Result<T, SkipReason>
+where
+    R: Read + 'a,
+    W: Fn(Stream<Box<dyn Read + 'a>>) -> io::Result<T>,
+{
+    let compression = options.compression(name);
+    read_stream(reader, compression, compression.is_some(), options, work)
+}
+
+/// Reads a file's contents as a [Stream], decompressing it if needed,
+/// and passes it to `work` unless it should be skipped. Failures to read
+/// `packed` data, from a compressed file or an archive, mean the data
+/// itself is corrupt.
+fn read_stream<'a, T, R, W>(
+    reader: R,
+    compression: Option<Compression>,
+    packed: bool,
+    options: &ReadOptions,
+    work: W,
+) -> Result<T, SkipReason>
+where
+    R: Read + 'a,
+    W: Fn(Stream<Box<dyn Read + 'a>>) -> io::Result<T>,
+{
+    let reason = |e: io::Error| match e.kind() {
+        ErrorKind::NotFound | ErrorKind::PermissionDenied => SkipReason::from(&e),
+        _ if packed => SkipReason::Corrupt,
+        _ => SkipReason::from(&e),
+    };
+
+    let reader: Box<dyn Read + 'a> = match compression {
+        Some(format) => format.decoder(reader).map_err(reason)?,
+        None => Box::new(reader),
+    };
+
+    let stream = Stream::new(reader).map_err(reason)?;
+
+    if !options.binary && stream.is_binary() {
+        return Err(SkipReason::Binary);
+    }
+
+    work(stream).map_err(reason)
 }
 
 #[cfg(test)]
 mod threads_tests {
+    use std::fs;
+    use std::path::Path;
     use std::sync::mpsc;
     use std::{path::PathBuf, str::FromStr};
 
     use super::handle;
+    use super::handle_diff;
+    use super::handle_hygiene;
+    use super::handle_hygiene_reader;
     use super::handle_in_thread;
+    use super::handle_reader;
+    use super::ReadOptions;
+    use crate::diff::Side;
+    use crate::summary::SkipReason;
+    use crate::words::{Metric, WordMode};
 
     #[test]
     fn threads_handle_lines() {
         let path = vec![PathBuf::from_str("tests/data/data.rs").unwrap()];
-        let result = handle(path, false);
+        let metric = Metric::new(false, &WordMode::Classic).unwrap();
+        let result = handle(path, &metric, &ReadOptions::default()).count;
         assert_eq!(result, 16);
     }
 
     #[test]
     fn threads_handle_words() {
         let path = vec![PathBuf::from_str("tests/data/data.rs").unwrap()];
-        let result = handle(path, true);
+        let metric = Metric::new(true, &WordMode::Classic).unwrap();
+        let result = handle(path, &metric, &ReadOptions::default()).count;
         assert_eq!(result, 36);
     }
 
@@ -57,7 +406,10 @@ mod threads_tests {
     fn threads_handle_in_thread_lines() {
         let path = vec![PathBuf::from_str("tests/data/data.rs").unwrap()];
         let (tx, rx) = mpsc::channel();
-        handle_in_thread(tx.clone(), path, false);
+        let metric = Metric::new(false, &WordMode::Classic).unwrap();
+        handle_in_thread(tx.clone(), path, move |f| {
+            handle(f, &metric, &ReadOptions::default()).count
+        });
         let result = rx.recv().unwrap();
         assert_eq!(result, 16);
     }
@@ -66,8 +418,180 @@ mod threads_tests {
     fn threads_handle_in_thread_words() {
         let path = vec![PathBuf::from_str("tests/data/data.rs").unwrap()];
         let (tx, rx) = mpsc::channel();
-        handle_in_thread(tx.clone(), path, true);
+        let metric = Metric::new(true, &WordMode::Classic).unwrap();
+        handle_in_thread(tx.clone(), path, move |f| {
+            handle(f, &metric, &ReadOptions::default()).count
+        });
         let result = rx.recv().unwrap();
         assert_eq!(result, 36);
     }
+
+    #[test]
+    fn threads_handle_words_unicode() {
+        let path = vec![PathBuf::from_str("tests/data/data.txt").unwrap()];
+        let metric = Metric::new(true, &WordMode::Unicode).unwrap();
+        let result = handle(path, &metric, &ReadOptions::default()).count;
+        assert_eq!(result, 44);
+    }
+
+    #[test]
+    fn threads_handle_hygiene() {
+        let path = vec![
+            PathBuf::from_str("tests/data/data.rs").unwrap(),
+            PathBuf::from_str("tests/data/data.py").unwrap(),
+        ];
+
+        let result = handle_hygiene(path, &ReadOptions::default());
+        assert_eq!(result.files.len(), 2);
+        assert_eq!(result.languages["Rust"].space_indented, 8);
+        assert_eq!(result.languages["Python"].max_indent, 8);
+        assert_eq!(result.total.mixed_files, 0);
+    }
+
+    #[test]
+    fn threads_handle_hygiene_reader() {
+        let options = ReadOptions::default();
+        let result = handle_hygiene_reader(&b"a \n\tb\n"[..], "-".into(), &options);
+        assert_eq!(result.files[&PathBuf::from("-")].trailing_whitespace, 1);
+        assert_eq!(result.total.tab_indented, 1);
+
+        let result = handle_hygiene_reader(&b"\0\x01\x02"[..], "-".into(), &options);
+        assert!(result.files.is_empty());
+    }
+
+    #[test]
+    fn threads_handle_binary() {
+        let path = vec![
+            PathBuf::from_str("tests/binary/blob.bin").unwrap(),
+            PathBuf::from_str("tests/data/data.rs").unwrap(),
+        ];
+
+        let metric = Metric::new(false, &WordMode::Classic).unwrap();
+        let mut options = ReadOptions::default();
+        let result = handle(path.clone(), &metric, &options);
+        assert_eq!(result.count, 16);
+        assert_eq!(result.files, 1);
+        assert_eq!(result.skipped_for(SkipReason::Binary), 1);
+
+        options.binary = true;
+        let result = handle(path, &metric, &options);
+        assert_eq!(result.files, 2);
+        assert!(result.skipped.is_empty());
+    }
+
+    #[test]
+    fn threads_handle_skipped() {
+        let path = vec![
+            PathBuf::from_str("tests/data/vanished.rs").unwrap(),
+            PathBuf::from_str("tests/data/data.rs").unwrap(),
+        ];
+
+        let metric = Metric::new(false, &WordMode::Classic).unwrap();
+        let result = handle(path, &metric, &ReadOptions::default());
+        assert_eq!(result.files, 1);
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].reason, SkipReason::Vanished);
+    }
+
+    #[test]
+    fn threads_handle_compressed() {
+        let path = vec![
+            PathBuf::from_str("tests/compressed/access.log.gz").unwrap(),
+            PathBuf::from_str("tests/compressed/access.log.zst").unwrap(),
+        ];
+
+        let metric = Metric::new(false, &WordMode::Classic).unwrap();
+        let mut options = ReadOptions::default();
+        let result = handle(path.clone(), &metric, &options);
+        assert_eq!(result.skipped_for(SkipReason::Binary), 2);
+
+        options.decompress = true;
+        let result = handle(path, &metric, &options);
+        assert_eq!(result.count, 10);
+        assert_eq!(result.files, 2);
+    }
+
+    #[test]
+    fn threads_handle_too_large() {
+        let path = vec![PathBuf::from_str("tests/data/data.rs").unwrap()];
+        let metric = Metric::new(false, &WordMode::Classic).unwrap();
+        let options = ReadOptions {
+            max_file_size: Some(64),
+            ..ReadOptions::default()
+        };
+
+        let result = handle(path.clone(), &metric, &options);
+        assert_eq!(result.files, 0);
+        assert_eq!(result.skipped_for(SkipReason::TooLarge), 1);
+
+        let options = ReadOptions {
+            max_file_size: Some(4096),
+            ..ReadOptions::default()
+        };
+        assert_eq!(handle(path, &metric, &options).count, 16);
+    }
+
+    #[test]
+    fn threads_handle_corrupt() {
+        let path = vec![PathBuf::from_str("tests/data/data.txt").unwrap()];
+        let metric = Metric::new(false, &WordMode::Classic).unwrap();
+        let options = ReadOptions {
+            decompress: true,
+            ..ReadOptions::default()
+        };
+
+        // Not compressed, so decompression is never attempted
+        assert_eq!(handle(path, &metric, &options).files, 1);
+
+        let path = vec![PathBuf::from_str("tests/compressed/corrupt.log.gz").unwrap()];
+        let result = handle(path, &metric, &options);
+        assert_eq!(result.skipped_for(SkipReason::Corrupt), 1);
+    }
+
+    #[test]
+    fn threads_handle_reader() {
+        let metric = Metric::new(true, &WordMode::Whitespace).unwrap();
+        let options = ReadOptions::default();
+        let result = handle_reader(&b"one two\nthree\n"[..], "-".into(), &metric, &options);
+        assert_eq!(result.count, 3);
+        assert_eq!(result.per_file[&PathBuf::from("-")], 3);
+
+        let result = handle_reader(&b"\0\x01\x02"[..], "-".into(), &metric, &options);
+        assert_eq!(result.skipped_for(SkipReason::Binary), 1);
+    }
+
+    #[test]
+    fn threads_handle_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old");
+        let new = dir.path().join("new");
+        fs::create_dir_all(&old).unwrap();
+        fs::create_dir_all(&new).unwrap();
+        fs::write(old.join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(new.join("main.rs"), "// main\nfn main() {}\n").unwrap();
+        fs::write(old.join("gone.rs"), "x\n").unwrap();
+        fs::write(new.join("blob.rs"), b"\0\x01").unwrap();
+
+        let side = |root: &Path, names: &[&str]| Side {
+            root: root.to_owned(),
+            files: names
+                .iter()
+                .map(|n| (PathBuf::from(n), root.join(n)))
+                .collect(),
+            ..Side::default()
+        };
+        let old = side(&old, &["main.rs", "gone.rs"]);
+        let new = side(&new, &["main.rs", "blob.rs"]);
+
+        let names = ["blob.rs", "gone.rs", "main.rs"]
+            .map(PathBuf::from)
+            .to_vec();
+        let (deltas, skipped) = handle_diff(names, &old, &new, &ReadOptions::default());
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(deltas[0].0, PathBuf::from("gone.rs"));
+        assert_eq!(deltas[0].1.code.removed, 1);
+        assert_eq!(deltas[1].1.comments.added, 1);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].reason, SkipReason::Binary);
+    }
 }
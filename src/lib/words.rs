@@ -0,0 +1,202 @@
+use std::io::{Error, ErrorKind, Read, Result};
+use std::str::FromStr;
+
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::stream::Stream;
+
+const CLASSIC_PATTERN: &str = r#"(\w?\\?['"]?\w+\\?['"]?(\w+)?)+"#;
+const IDENTIFIER_PATTERN: &str = r"[A-Za-z_][A-Za-z0-9_]*";
+
+/// The strategy used to split text into words.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum WordMode {
+    /// The original xloc word pattern, which treats quotes and
+    /// escapes inside a word as part of that word.
+    Classic,
+
+    /// Words as defined by Unicode text segmentation
+    /// ([UAX #29](https://www.unicode.org/reports/tr29/)). Punctuation
+    /// and emoji are not words, and each CJK ideograph is its own word.
+    Unicode,
+
+    /// Any run of non-whitespace characters, matching `wc -w`.
+    Whitespace,
+
+    /// Identifier-like code tokens, matching `[A-Za-z_][A-Za-z0-9_]*`.
+    Identifier,
+
+    /// Every non-overlapping match of a custom regular expression.
+    Custom(String),
+}
+
+impl Default for WordMode {
+    /// ```
+    /// use xloc::WordMode;
+    ///
+    /// assert_eq!(WordMode::default(), WordMode::Classic);
+    /// ```
+    fn default() -> Self {
+        Self::Classic
+    }
+}
+
+impl FromStr for WordMode {
+    type Err = Error;
+
+    /// ```
+    /// use xloc::WordMode;
+    ///
+    /// assert_eq!("unicode".parse::<WordMode>().unwrap(), WordMode::Unicode);
+    /// assert!("sentences".parse::<WordMode>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "classic" => Ok(Self::Classic),
+            "unicode" => Ok(Self::Unicode),
+            "whitespace" => Ok(Self::Whitespace),
+            "identifier" => Ok(Self::Identifier),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown word mode: {}", s),
+            )),
+        }
+    }
+}
+
+/// What a worker counts in each file, built once per count and shared
+/// between threads.
+#[derive(Debug, Clone)]
+pub enum Metric {
+    Lines,
+    Pattern(Regex),
+    Unicode,
+    Whitespace,
+}
+
+impl Metric {
+    pub fn new(words: bool, mode: &WordMode) -> Result<Self> {
+        if !words {
+            return Ok(Self::Lines);
+        }
+
+        let pattern = match mode {
+            WordMode::Classic => CLASSIC_PATTERN,
+            WordMode::Identifier => IDENTIFIER_PATTERN,
+            WordMode::Custom(pattern) => pattern,
+            WordMode::Unicode => return Ok(Self::Unicode),
+            WordMode::Whitespace => return Ok(Self::Whitespace),
+        };
+
+        match Regex::new(pattern) {
+            Ok(rgx) => Ok(Self::Pattern(rgx)),
+            Err(e) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid word pattern: {}", e),
+            )),
+        }
+    }
+
+    /// Counts the contents of a stream, whatever its encoding. Lines
+    /// are counted on the raw bytes unless the stream is UTF-16.
+    pub fn count_stream<R: Read>(&self, stream: Stream<R>) -> Result<usize> {
+        if let Self::Lines = self {
+            if !stream.is_wide() {
+                return stream.count_newlines();
+            }
+        }
+
+        let mut total = 0;
+        stream.for_each_text(|text| total += self.count(text))?;
+        Ok(total)
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            Self::Lines => text.bytes().filter(|b| *b == b'\n').count(),
+            Self::Pattern(rgx) => rgx.find_iter(text).count(),
+            Self::Unicode => text.unicode_words().count(),
+            Self::Whitespace => text.split_whitespace().count(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod words_tests {
+    use super::{Metric, WordMode};
+    use crate::stream::Stream;
+
+    #[test]
+    fn words_metric_lines() {
+        let metric = Metric::new(false, &WordMode::Unicode).unwrap();
+        assert_eq!(metric.count("one\ntwo\n"), 2);
+    }
+
+    #[test]
+    fn words_metric_classic() {
+        let metric = Metric::new(true, &WordMode::Classic).unwrap();
+        assert_eq!(metric.count("Fearless-concurrency! I'm here"), 4);
+    }
+
+    #[test]
+    fn words_metric_unicode() {
+        let metric = Metric::new(true, &WordMode::Unicode).unwrap();
+        assert_eq!(metric.count("Fearless-concurrency! I'm here"), 4);
+        assert_eq!(metric.count("日本語のテキスト"), 5);
+        assert_eq!(metric.count("ship it 🚀 -- done."), 3);
+    }
+
+    #[test]
+    fn words_metric_whitespace() {
+        let metric = Metric::new(true, &WordMode::Whitespace).unwrap();
+        assert_eq!(metric.count("Fearless-concurrency! I'm here"), 3);
+        assert_eq!(metric.count("ship it 🚀 -- done."), 5);
+        assert_eq!(metric.count("日本語のテキスト"), 1);
+    }
+
+    #[test]
+    fn words_metric_identifier() {
+        let metric = Metric::new(true, &WordMode::Identifier).unwrap();
+        assert_eq!(metric.count("let x_1 = foo(2, \"bar baz\");"), 5);
+    }
+
+    #[test]
+    fn words_metric_custom() {
+        let mode = WordMode::Custom(r"\d+".to_owned());
+        let metric = Metric::new(true, &mode).unwrap();
+        assert_eq!(metric.count("1 fish, 2 fish, 30 fish"), 3);
+
+        let mode = WordMode::Custom(r"(unclosed".to_owned());
+        assert!(Metric::new(true, &mode).is_err());
+    }
+
+    #[test]
+    fn words_metric_count_stream() {
+        let lines = Metric::new(false, &WordMode::Classic).unwrap();
+        let words = Metric::new(true, &WordMode::Whitespace).unwrap();
+        let count = |metric: &Metric, bytes: &[u8]| {
+            metric.count_stream(Stream::new(bytes).unwrap()).unwrap()
+        };
+
+        assert_eq!(count(&lines, b"caf\xE9\n\xFF\xFE\n"), 2);
+        assert_eq!(count(&lines, b"\xFF\xFEa\0\n\0\n\x0A"), 1);
+        assert_eq!(count(&words, b"caf\xE9 cr\xE8me\n"), 2);
+        assert_eq!(count(&words, b"\xFE\xFF\0a\0 \0b"), 2);
+    }
+
+    #[test]
+    fn words_mode_from_str() {
+        assert_eq!("classic".parse::<WordMode>().unwrap(), WordMode::Classic);
+        assert_eq!("unicode".parse::<WordMode>().unwrap(), WordMode::Unicode);
+        assert_eq!(
+            "whitespace".parse::<WordMode>().unwrap(),
+            WordMode::Whitespace
+        );
+        assert_eq!(
+            "identifier".parse::<WordMode>().unwrap(),
+            WordMode::Identifier
+        );
+        assert!("nope".parse::<WordMode>().is_err());
+    }
+}
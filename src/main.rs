@@ -1,27 +1,271 @@
 mod parser;
 
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
 use std::process;
 
-use parser::Parser;
-use xloc::App;
+use parser::{DiffArgs, Parser, STDIN};
+use xloc::{App, HygieneReport, Skipped, Summary, DEFAULT_EXCLUDES};
 
 fn main() {
     let parser = Parser::new();
-    let app = App::new(parser.njobs, parser.words);
 
-    let total = parser
-        .paths
-        .iter()
-        .map(|p| match app.count(p) {
-            Ok(count) => count,
+    if parser.list_default_excludes {
+        for name in DEFAULT_EXCLUDES {
+            println!("{}", name);
+        }
+
+        return;
+    }
+
+    let mut app = App::new(parser.njobs, parser.words);
+    app.set_word_mode(parser.word_mode);
+    app.set_binary(parser.binary);
+    app.set_decompress(parser.decompress);
+    app.set_archives(parser.archives);
+    app.set_ignore(!parser.no_ignore);
+    app.set_include(parser.include.clone());
+    app.set_exclude(parser.exclude.clone());
+    app.set_symlinks(parser.symlinks);
+    app.set_hidden(parser.hidden);
+    app.set_max_depth(parser.max_depth);
+    app.set_max_file_size(parser.max_file_size);
+    app.set_newer_than(parser.newer_than);
+    app.set_older_than(parser.older_than);
+    app.set_one_file_system(parser.one_file_system);
+    app.set_git_tracked(parser.git_tracked);
+    app.set_rev(parser.rev);
+    app.set_mailmap(parser.mailmap);
+
+    if parser.no_default_excludes {
+        app.set_default_excludes(vec![]);
+    }
+
+    if let Some(names) = &parser.default_excludes {
+        app.set_default_excludes(names.clone());
+    }
+
+    let paths = parser.paths.iter().map(|p| p.as_str()).collect::<Vec<_>>();
+
+    if let Some(args) = &parser.diff {
+        let report = match args {
+            DiffArgs::Trees(old, new) => app.diff(old, new),
+            DiffArgs::Staged => app.diff_staged(single_path(&paths, "diff --staged")),
+            DiffArgs::Unstaged => app.diff_unstaged(single_path(&paths, "diff --unstaged")),
+        };
+
+        let report = match report {
+            Ok(report) => report,
             Err(e) => {
                 println!("{}", e);
                 process::exit(1);
             }
-        })
-        .sum::<usize>();
+        };
+
+        print!("{}", report);
+        report_skipped(&report.skipped, parser.show_skipped);
+
+        if parser.fail_on_skipped && !report.skipped.is_empty() {
+            process::exit(2);
+        }
+
+        return;
+    }
+
+    if let Some(args) = &parser.history {
+        let history = match app.history(single_path(&paths, "history"), &args.rev, args.sampling) {
+            Ok(history) => history,
+            Err(e) => {
+                println!("{}", e);
+                process::exit(1);
+            }
+        };
+
+        match args.json {
+            true => print!("{}", history.to_json()),
+            false => print!("{}", history.to_csv()),
+        }
+
+        report_skipped(&history.skipped, parser.show_skipped);
+
+        if parser.fail_on_skipped && !history.skipped.is_empty() {
+            process::exit(2);
+        }
+
+        return;
+    }
+
+    if parser.blame {
+        // Blame needs files in a repository, which stdin isn't
+        if paths.contains(&STDIN) {
+            println!("--blame can't read from stdin, pass the files to blame instead");
+            process::exit(1);
+        }
+
+        let authorship = match app.blame_all(&paths) {
+            Ok(authorship) => authorship,
+            Err(e) => {
+                println!("{}", e);
+                process::exit(1);
+            }
+        };
+
+        print!("{}", authorship);
+        report_skipped(&authorship.skipped, parser.show_skipped);
+
+        if parser.fail_on_skipped && !authorship.skipped.is_empty() {
+            process::exit(2);
+        }
+
+        return;
+    }
+
+    if parser.hygiene {
+        let report = match &parser.files_from {
+            Some(source) => read_files_from(source).and_then(|f| app.hygiene_files(f)),
+            None => hygiene_paths(&app, &paths),
+        };
+
+        let report = match report {
+            Ok(report) => report,
+            Err(e) => {
+                println!("{}", e);
+                process::exit(1);
+            }
+        };
+
+        print!("{}", report);
+        report_skipped(&report.skipped, parser.show_skipped);
 
-    println!("{}", total);
+        if parser.fail_on_skipped && !report.skipped.is_empty() {
+            process::exit(2);
+        }
+
+        return;
+    }
+
+    let summary = match &parser.files_from {
+        Some(source) => read_files_from(source).and_then(|f| app.summarize_files(f)),
+        None => summarize_paths(&app, &paths),
+    };
+
+    let summary = match summary {
+        Ok(summary) => summary,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    if parser.per_file {
+        for (file, count) in &summary.per_file {
+            println!("{:>8} {}", count, file.display());
+        }
+    }
+
+    if parser.per_root {
+        for (root, count) in &summary.per_root {
+            println!("{:>8} {}", count, root.display());
+        }
+    }
+
+    println!("{}", summary.count);
+    report_skipped(&summary.skipped, parser.show_skipped);
+
+    if parser.fail_on_skipped && !summary.skipped.is_empty() {
+        process::exit(2);
+    }
+}
+
+/// The path of a mode that only works on one tree, exiting if several
+/// were given.
+fn single_path<'a>(paths: &[&'a str], mode: &str) -> &'a str {
+    match paths {
+        [path] => path,
+        _ => {
+            println!("{} takes a single path", mode);
+            process::exit(1);
+        }
+    }
+}
+
+/// Counts every path, and stdin if one of them is `-`.
+fn summarize_paths(app: &App, paths: &[&str]) -> io::Result<Summary> {
+    let (stdin, paths): (Vec<&str>, Vec<&str>) = paths.iter().partition(|p| **p == STDIN);
+    let mut summary = app.summarize_all(&paths)?;
+
+    if !stdin.is_empty() {
+        let mut rcvd = app.summarize_reader(io::stdin().lock(), STDIN)?;
+        rcvd.per_root.push((STDIN.into(), rcvd.count));
+        summary.add(&rcvd);
+        summary.finish();
+    }
+
+    Ok(summary)
+}
+
+/// Builds the hygiene report of every path, and stdin if one of them is
+/// `-`.
+fn hygiene_paths(app: &App, paths: &[&str]) -> io::Result<HygieneReport> {
+    let (stdin, paths): (Vec<&str>, Vec<&str>) = paths.iter().partition(|p| **p == STDIN);
+    let mut report = app.hygiene_all(&paths)?;
+
+    if !stdin.is_empty() {
+        report.merge(app.hygiene_reader(io::stdin().lock(), STDIN));
+    }
+
+    Ok(report)
+}
+
+/// Reads a list of files from a file, or stdin if `source` is `-`. The
+/// list is NUL separated if it contains a NUL, like `find -print0`
+/// output, or has one file per line otherwise.
+fn read_files_from(source: &str) -> io::Result<Vec<PathBuf>> {
+    let mut list = vec![];
+
+    match source {
+        "-" => io::stdin().lock().read_to_end(&mut list)?,
+        _ => File::open(source)?.read_to_end(&mut list)?,
+    };
+
+    let separator = if list.contains(&0) { 0 } else { b'\n' };
+
+    Ok(list
+        .split(|b| *b == separator)
+        .map(|entry| entry.strip_suffix(b"\r").unwrap_or(entry))
+        .filter(|entry| !entry.is_empty())
+        .map(path_from_bytes)
+        .collect())
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn report_skipped(skipped: &[Skipped], show: bool) {
+    if show {
+        for skipped in skipped {
+            eprintln!("{}: {}", skipped.path.display(), skipped.reason);
+        }
+    }
+
+    let reasons = skipped.iter().map(|s| s.reason).collect::<BTreeSet<_>>();
+
+    for reason in reasons {
+        let nskipped = skipped.iter().filter(|s| s.reason == reason).count();
+        eprintln!("skipped {} file(s): {}", nskipped, reason);
+    }
 }
 
 #[cfg(test)]
@@ -1,9 +1,65 @@
-use clap::{App, Arg};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use clap::{App, AppSettings, Arg, SubCommand};
+use xloc::{Sampling, SymlinkPolicy, WordMode};
+
+/// The path that stands for standard input.
+pub const STDIN: &str = "-";
+
+/// What the `diff` subcommand compares.
+#[derive(Debug, Eq, PartialEq)]
+pub enum DiffArgs {
+    /// Two directories or git revisions.
+    Trees(String, String),
+
+    /// The index, compared to `HEAD`.
+    Staged,
+
+    /// The working tree, compared to the index.
+    Unstaged,
+}
+
+/// The arguments of the `history` subcommand.
+#[derive(Debug, Eq, PartialEq)]
+pub struct HistoryArgs {
+    pub rev: String,
+    pub sampling: Sampling,
+    pub json: bool,
+}
 
 pub struct Parser {
     pub njobs: usize,
     pub paths: Vec<String>,
     pub words: bool,
+    pub word_mode: WordMode,
+    pub hygiene: bool,
+    pub blame: bool,
+    pub mailmap: bool,
+    pub binary: bool,
+    pub decompress: bool,
+    pub archives: bool,
+    pub per_file: bool,
+    pub per_root: bool,
+    pub no_ignore: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub no_default_excludes: bool,
+    pub default_excludes: Option<Vec<String>>,
+    pub list_default_excludes: bool,
+    pub symlinks: SymlinkPolicy,
+    pub hidden: bool,
+    pub max_depth: Option<usize>,
+    pub max_file_size: Option<u64>,
+    pub one_file_system: bool,
+    pub newer_than: Option<SystemTime>,
+    pub older_than: Option<SystemTime>,
+    pub files_from: Option<String>,
+    pub git_tracked: bool,
+    pub rev: Option<String>,
+    pub diff: Option<DiffArgs>,
+    pub history: Option<HistoryArgs>,
+    pub show_skipped: bool,
+    pub fail_on_skipped: bool,
 }
 
 impl Parser {
@@ -11,6 +67,10 @@ impl Parser {
         let cli = App::new("xloc")
             .version("0.1.0")
             .about("A fast, multi-threaded line counting utility")
+            // A path that is merely close to a subcommand's name, like
+            // `./diff`, or any path after `--`, is counted rather than
+            // rejected as a mistyped subcommand
+            .setting(AppSettings::AllowExternalSubcommands)
             .arg(
                 Arg::with_name("jobs")
                     .short("j")
@@ -27,23 +87,376 @@ impl Parser {
                     .help("If included, count words instead of lines")
                     .takes_value(false),
             )
+            .arg(
+                Arg::with_name("word-mode")
+                    .long("word-mode")
+                    .value_name("MODE")
+                    .help("How to split words, implies --words [default: classic]")
+                    .takes_value(true)
+                    .possible_values(&["classic", "unicode", "whitespace", "identifier"]),
+            )
+            .arg(
+                Arg::with_name("word-regex")
+                    .long("word-regex")
+                    .value_name("REGEX")
+                    .help("Count matches of a custom regex as words, implies --words")
+                    .takes_value(true)
+                    .conflicts_with("word-mode"),
+            )
+            .arg(
+                Arg::with_name("binary")
+                    .long("binary")
+                    .help("Count binary files instead of skipping them")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("decompress")
+                    .short("z")
+                    .long("decompress")
+                    .help("Count the contents of .gz, .bz2, .xz and .zst files")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("archives")
+                    .long("archives")
+                    .help("Count the files inside .tar, .tar.gz, .tgz and .zip archives")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("per-file")
+                    .long("per-file")
+                    .help("Print the count of each file before the total")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("per-root")
+                    .long("per-root")
+                    .help("Print the count of each path argument before the total")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("no-ignore")
+                    .long("no-ignore")
+                    .help("Don't respect .gitignore, .ignore, .xlocignore or git excludes")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("include")
+                    .long("include")
+                    .value_name("GLOB")
+                    .help("Only count files matching a glob, may be repeated")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1),
+            )
+            .arg(
+                Arg::with_name("exclude")
+                    .long("exclude")
+                    .value_name("GLOB")
+                    .help("Leave out files and dirs matching a glob, may be repeated")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1),
+            )
+            .arg(
+                Arg::with_name("no-default-excludes")
+                    .long("no-default-excludes")
+                    .help("Don't skip VCS and build dirs like .git and target by default")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("default-excludes")
+                    .long("default-excludes")
+                    .value_name("NAME,...")
+                    .help("Skip these names everywhere instead of the default ones")
+                    .takes_value(true)
+                    .use_delimiter(true)
+                    .conflicts_with("no-default-excludes"),
+            )
+            .arg(
+                Arg::with_name("list-default-excludes")
+                    .long("list-default-excludes")
+                    .help("Print the names skipped by default, then exit")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("symlinks")
+                    .long("symlinks")
+                    .value_name("POLICY")
+                    .help("Which symlinks to follow [default: always]")
+                    .takes_value(true)
+                    .possible_values(&["never", "within-root", "always"]),
+            )
+            .arg(
+                Arg::with_name("hidden")
+                    .long("hidden")
+                    .help("Count hidden files and dirs, whose names start with a .")
+                    .takes_value(false)
+                    .overrides_with("no-hidden"),
+            )
+            .arg(
+                Arg::with_name("no-hidden")
+                    .long("no-hidden")
+                    .help("Skip hidden files and dirs [default]")
+                    .takes_value(false)
+                    .overrides_with("hidden"),
+            )
+            .arg(
+                Arg::with_name("max-depth")
+                    .long("max-depth")
+                    .value_name("NUM")
+                    .help("How many directories deep to search")
+                    .takes_value(true)
+                    .validator(|n| match n.parse::<usize>() {
+                        Ok(_) => Ok(()),
+                        Err(_) => Err(String::from("must be a whole number")),
+                    }),
+            )
+            .arg(
+                Arg::with_name("max-file-size")
+                    .long("max-file-size")
+                    .value_name("SIZE")
+                    .help("Skip files larger than SIZE bytes, or with a K, M or G suffix")
+                    .takes_value(true)
+                    .validator(|size| match parse_size(&size) {
+                        Some(_) => Ok(()),
+                        None => Err(String::from("must be a size like 512, 64K or 1G")),
+                    }),
+            )
+            .arg(
+                Arg::with_name("newer-than")
+                    .long("newer-than")
+                    .value_name("DATE|DURATION")
+                    .help("Only count files modified after a date like 2024-01-31, or within a duration like 30d")
+                    .takes_value(true)
+                    .validator(validate_time)
+                    .conflicts_with_all(&["rev", "files-from"]),
+            )
+            .arg(
+                Arg::with_name("older-than")
+                    .long("older-than")
+                    .value_name("DATE|DURATION")
+                    .help("Only count files modified before a date like 2024-01-31, or longer ago than a duration like 1y")
+                    .takes_value(true)
+                    .validator(validate_time)
+                    .conflicts_with_all(&["rev", "files-from"]),
+            )
+            .arg(
+                Arg::with_name("one-file-system")
+                    .short("x")
+                    .long("one-file-system")
+                    .help("Don't search directories on other filesystems")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("files-from")
+                    .long("files-from")
+                    .value_name("FILE")
+                    .help("Count the files listed in FILE, or stdin if -, instead of the paths")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("git-tracked")
+                    .long("git-tracked")
+                    .help("Only count files tracked in the git index")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("rev")
+                    .long("rev")
+                    .value_name("REV")
+                    .help("Count the files of a git commit, tag or branch, without checking it out")
+                    .takes_value(true)
+                    .conflicts_with_all(&["git-tracked", "files-from"]),
+            )
+            .arg(
+                Arg::with_name("show-skipped")
+                    .long("show-skipped")
+                    .help("List every skipped file and the reason it was skipped")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("fail-on-skipped")
+                    .long("fail-on-skipped")
+                    .help("Exit with status 2 if any file was skipped")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("hygiene")
+                    .long("hygiene")
+                    .help("Report trailing whitespace and indentation style instead of counting")
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("blame")
+                    .long("blame")
+                    .help("Report lines per author and language from git blame instead of counting")
+                    .takes_value(false)
+                    .conflicts_with_all(&["hygiene", "rev", "files-from"]),
+            )
+            .arg(
+                Arg::with_name("mailmap")
+                    .long("mailmap")
+                    .help("Apply the repository's .mailmap to authors, with --blame")
+                    .takes_value(false)
+                    .requires("blame"),
+            )
             .arg(
                 Arg::with_name("path")
-                    .help("The path or paths to parse")
+                    .help("The path or paths to parse, or - for stdin")
                     .takes_value(true)
                     .multiple(true)
                     .required(true)
                     .default_value("."),
+            )
+            .subcommand(
+                SubCommand::with_name("diff")
+                    .about("Report the code, comment and blank lines added and removed between two trees")
+                    .arg(
+                        Arg::with_name("staged")
+                            .long("staged")
+                            .help("Compare the changes staged in the index to HEAD")
+                            .takes_value(false)
+                            .conflicts_with_all(&["unstaged", "old"]),
+                    )
+                    .arg(
+                        Arg::with_name("unstaged")
+                            .long("unstaged")
+                            .help("Compare the working tree to the index")
+                            .takes_value(false)
+                            .conflicts_with("old"),
+                    )
+                    .arg(
+                        Arg::with_name("old")
+                            .help("The directory or git revision to compare from")
+                            .required_unless_one(&["staged", "unstaged"])
+                            .index(1),
+                    )
+                    .arg(
+                        Arg::with_name("new")
+                            .help("The directory or git revision to compare to")
+                            .required_unless_one(&["staged", "unstaged"])
+                            .index(2),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("history")
+                    .about("Count each language at points in the git history of the current directory")
+                    .arg(
+                        Arg::with_name("sample")
+                            .long("sample")
+                            .value_name("EVERY")
+                            .help("Which commits to count [default: commit]")
+                            .takes_value(true)
+                            .possible_values(&["commit", "day", "week", "tag"]),
+                    )
+                    .arg(
+                        Arg::with_name("format")
+                            .long("format")
+                            .value_name("FORMAT")
+                            .help("How to print the counts")
+                            .takes_value(true)
+                            .possible_values(&["csv", "json"])
+                            .default_value("csv"),
+                    )
+                    .arg(
+                        Arg::with_name("rev")
+                            .help("The revision whose history is walked")
+                            .default_value("HEAD")
+                            .index(1),
+                    ),
             );
 
         let matches = cli.get_matches();
-        let words = matches.is_present("words");
+        let words = matches.is_present("words")
+            || matches.is_present("word-mode")
+            || matches.is_present("word-regex");
 
-        let paths = matches
-            .values_of("path")
-            .unwrap()
-            .map(|p| p.to_string())
-            .collect::<Vec<String>>();
+        let word_mode = match matches.value_of("word-regex") {
+            Some(pattern) => WordMode::Custom(pattern.to_string()),
+            None => matches
+                .value_of("word-mode")
+                .map(|m| m.parse::<WordMode>().unwrap())
+                .unwrap_or_default(),
+        };
+
+        let hygiene = matches.is_present("hygiene");
+        let blame = matches.is_present("blame");
+        let mailmap = matches.is_present("mailmap");
+        let binary = matches.is_present("binary");
+        let decompress = matches.is_present("decompress");
+        let archives = matches.is_present("archives");
+        let per_file = matches.is_present("per-file");
+        let per_root = matches.is_present("per-root");
+        let no_ignore = matches.is_present("no-ignore");
+        let globs = |name| {
+            matches
+                .values_of(name)
+                .map(|v| v.map(|g| g.to_string()).collect::<Vec<String>>())
+                .unwrap_or_default()
+        };
+
+        let include = globs("include");
+        let exclude = globs("exclude");
+        let no_default_excludes = matches.is_present("no-default-excludes");
+        let default_excludes = matches
+            .values_of("default-excludes")
+            .map(|v| v.map(|n| n.to_string()).collect::<Vec<String>>());
+        let list_default_excludes = matches.is_present("list-default-excludes");
+        let symlinks = matches
+            .value_of("symlinks")
+            .map(|p| p.parse::<SymlinkPolicy>().unwrap())
+            .unwrap_or_default();
+
+        let hidden = matches.is_present("hidden");
+        let max_depth = matches
+            .value_of("max-depth")
+            .and_then(|n| n.parse::<usize>().ok());
+        let max_file_size = matches.value_of("max-file-size").and_then(parse_size);
+        let one_file_system = matches.is_present("one-file-system");
+        let now = SystemTime::now();
+        let newer_than = matches
+            .value_of("newer-than")
+            .and_then(|t| parse_time(t, now));
+        let older_than = matches
+            .value_of("older-than")
+            .and_then(|t| parse_time(t, now));
+        let files_from = matches.value_of("files-from").map(|f| f.to_string());
+        let git_tracked = matches.is_present("git-tracked");
+        let rev = matches.value_of("rev").map(|r| r.to_string());
+        let diff = matches.subcommand_matches("diff").map(|m| {
+            let side = |name| m.value_of(name).unwrap().to_string();
+
+            match (m.is_present("staged"), m.is_present("unstaged")) {
+                (true, _) => DiffArgs::Staged,
+                (_, true) => DiffArgs::Unstaged,
+                _ => DiffArgs::Trees(side("old"), side("new")),
+            }
+        });
+        let history = matches.subcommand_matches("history").map(|m| HistoryArgs {
+            rev: m.value_of("rev").unwrap().to_string(),
+            sampling: m
+                .value_of("sample")
+                .map(|s| s.parse::<Sampling>().unwrap())
+                .unwrap_or_default(),
+            json: m.value_of("format") == Some("json"),
+        });
+
+        let show_skipped = matches.is_present("show-skipped");
+        let fail_on_skipped = matches.is_present("fail-on-skipped");
+
+        let reads_repository =
+            blame || git_tracked || rev.is_some() || diff.is_some() || history.is_some();
+        let paths = choose_paths(
+            matches
+                .values_of("path")
+                .unwrap()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>(),
+            matches.occurrences_of("path") > 0,
+            reads_repository,
+            stdin_is_piped(),
+        );
 
         let njobs = matches
             .value_of("jobs")
@@ -55,15 +468,191 @@ impl Parser {
             njobs,
             paths,
             words,
+            word_mode,
+            hygiene,
+            blame,
+            mailmap,
+            binary,
+            decompress,
+            archives,
+            per_file,
+            per_root,
+            no_ignore,
+            include,
+            exclude,
+            no_default_excludes,
+            default_excludes,
+            list_default_excludes,
+            symlinks,
+            hidden,
+            max_depth,
+            max_file_size,
+            one_file_system,
+            newer_than,
+            older_than,
+            files_from,
+            git_tracked,
+            rev,
+            diff,
+            history,
+            show_skipped,
+            fail_on_skipped,
         }
     }
 }
 
+/// Parses a size in bytes, with an optional `K`, `M` or `G` suffix for
+/// kibibytes, mebibytes or gibibytes.
+fn parse_size(size: &str) -> Option<u64> {
+    let (digits, unit) = match size.char_indices().last()? {
+        (i, 'K' | 'k') => (&size[..i], 1 << 10),
+        (i, 'M' | 'm') => (&size[..i], 1 << 20),
+        (i, 'G' | 'g') => (&size[..i], 1 << 30),
+        _ => (size, 1),
+    };
+
+    digits.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+fn validate_time(time: String) -> Result<(), String> {
+    match parse_time(&time, SystemTime::now()) {
+        Some(_) => Ok(()),
+        None => Err(String::from(
+            "must be a date like 2024-01-31 or a duration like 30d",
+        )),
+    }
+}
+
+/// Parses a point in time, either a UTC date like `2024-01-31` or
+/// `2024-01-31T12:00:00`, or a duration before `now` like `90m`, `12h`,
+/// `30d`, `2w` or `1y`.
+fn parse_time(time: &str, now: SystemTime) -> Option<SystemTime> {
+    if time.contains('-') {
+        return parse_date(time);
+    }
+
+    let (digits, unit) = time.split_at(time.find(|c: char| !c.is_ascii_digit())?);
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        "y" => 365 * 86_400,
+        _ => return None,
+    };
+
+    let duration = digits.parse::<u64>().ok()?.checked_mul(seconds)?;
+    now.checked_sub(Duration::from_secs(duration))
+}
+
+/// Parses a UTC date like `2024-01-31`, with an optional time like
+/// `T12:00:00`.
+fn parse_date(date: &str) -> Option<SystemTime> {
+    let (date, time) = date.split_once('T').unwrap_or((date, "00:00:00"));
+    let parts = |s: &str, sep| {
+        s.split(sep)
+            .map(|n| n.parse::<i64>().ok())
+            .collect::<Option<Vec<_>>>()
+    };
+
+    let (year, month, day) = match parts(date, '-')?[..] {
+        [year, month, day] => (year, month, day),
+        _ => return None,
+    };
+    let (hour, minute, second) = match parts(time, ':')?[..] {
+        [hour, minute, second] => (hour, minute, second),
+        _ => return None,
+    };
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+
+    if !(1..=month_days).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    // Converts a civil date to days, from Howard Hinnant's algorithm
+    let y = year - i64::from(month <= 2);
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second;
+    let offset = Duration::from_secs(seconds.unsigned_abs());
+
+    match seconds >= 0 {
+        true => UNIX_EPOCH.checked_add(offset),
+        false => UNIX_EPOCH.checked_sub(offset),
+    }
+}
+
+/// The paths to count. Like wc, data piped in is counted when no paths
+/// are `given`, unless the mode `reads_repository`, as blame, git
+/// revisions, tracked files, history and changes can only be read from
+/// one.
+fn choose_paths(
+    paths: Vec<String>,
+    given: bool,
+    reads_repository: bool,
+    stdin_is_piped: bool,
+) -> Vec<String> {
+    if !given && !reads_repository && stdin_is_piped {
+        vec![STDIN.to_string()]
+    } else {
+        paths
+    }
+}
+
+/// Whether stdin is a pipe or a file, rather than a terminal or
+/// `/dev/null`.
+#[cfg(all(unix, not(test)))]
+fn stdin_is_piped() -> bool {
+    use std::fs::File;
+    use std::os::fd::AsFd;
+    use std::os::unix::fs::FileTypeExt;
+
+    std::io::stdin()
+        .as_fd()
+        .try_clone_to_owned()
+        .map(File::from)
+        .and_then(|f| f.metadata())
+        .map(|m| m.file_type().is_fifo() || m.is_file())
+        .unwrap_or(false)
+}
+
+/// Whether stdin is redirected, rather than a terminal.
+#[cfg(all(not(unix), not(test)))]
+fn stdin_is_piped() -> bool {
+    use std::io::IsTerminal;
+
+    !std::io::stdin().is_terminal()
+}
+
+/// Tests never count the test runner's own stdin, see [choose_paths]
+/// for when stdin is counted.
+#[cfg(test)]
+fn stdin_is_piped() -> bool {
+    false
+}
+
 #[cfg(test)]
 mod parser_tests {
-    use super::Parser;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{choose_paths, parse_size, parse_time, Parser};
+    use xloc::{SymlinkPolicy, WordMode};
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn new_parser() {
         let parser = Parser::new();
         assert_eq!(parser.njobs, 1);
@@ -72,6 +661,7 @@ mod parser_tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn mut_parser() {
         let mut parser = Parser::new();
         parser.njobs = 3;
@@ -82,4 +672,90 @@ mod parser_tests {
         assert_eq!(parser.paths, vec!["tests/data"]);
         assert_eq!(parser.words, true);
     }
+
+    #[test]
+    fn default_options() {
+        let parser = Parser::new();
+        assert_eq!(parser.word_mode, WordMode::Classic);
+        assert!(!parser.hygiene);
+        assert!(!parser.blame);
+        assert!(!parser.mailmap);
+        assert!(!parser.binary);
+        assert!(!parser.decompress);
+        assert!(!parser.archives);
+        assert!(!parser.per_file);
+        assert!(!parser.per_root);
+        assert!(!parser.no_ignore);
+        assert!(parser.include.is_empty());
+        assert!(parser.exclude.is_empty());
+        assert!(!parser.no_default_excludes);
+        assert_eq!(parser.default_excludes, None);
+        assert!(!parser.list_default_excludes);
+        assert_eq!(parser.symlinks, SymlinkPolicy::Always);
+        assert!(!parser.hidden);
+        assert_eq!(parser.max_depth, None);
+        assert_eq!(parser.max_file_size, None);
+        assert!(!parser.one_file_system);
+        assert_eq!(parser.newer_than, None);
+        assert_eq!(parser.older_than, None);
+        assert_eq!(parser.files_from, None);
+        assert!(!parser.git_tracked);
+        assert_eq!(parser.rev, None);
+        assert_eq!(parser.diff, None);
+        assert_eq!(parser.history, None);
+        assert!(!parser.show_skipped);
+        assert!(!parser.fail_on_skipped);
+    }
+
+    #[test]
+    fn choose_paths_stdin() {
+        let paths = || vec![".".to_string()];
+
+        assert_eq!(choose_paths(paths(), false, false, true), vec!["-"]);
+        assert_eq!(choose_paths(paths(), false, false, false), vec!["."]);
+
+        // Paths given, even just ".", win over stdin
+        assert_eq!(choose_paths(paths(), true, false, true), vec!["."]);
+
+        // Blame, --rev, --git-tracked, diff and history need a repository
+        assert_eq!(choose_paths(paths(), false, true, true), vec!["."]);
+    }
+
+    #[test]
+    fn parse_sizes() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("64K"), Some(64 * 1024));
+        assert_eq!(parse_size("2m"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_size("1G"), Some(1 << 30));
+        assert_eq!(parse_size("G"), None);
+        assert_eq!(parse_size("1.5M"), None);
+        assert_eq!(parse_size(""), None);
+    }
+
+    #[test]
+    fn parse_times() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let ago = |secs| Some(now - Duration::from_secs(secs));
+
+        assert_eq!(parse_time("90s", now), ago(90));
+        assert_eq!(parse_time("12h", now), ago(12 * 3600));
+        assert_eq!(parse_time("30d", now), ago(30 * 86_400));
+        assert_eq!(parse_time("2w", now), ago(14 * 86_400));
+        assert_eq!(parse_time("30", now), None);
+        assert_eq!(parse_time("d", now), None);
+        assert_eq!(parse_time("3x", now), None);
+
+        let date = |secs| Some(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(parse_time("1970-01-01", now), date(0));
+        assert_eq!(parse_time("2001-09-09T01:46:40", now), date(1_000_000_000));
+        assert_eq!(parse_time("2024-02-29", now), date(1_709_164_800));
+        assert_eq!(
+            parse_time("1969-12-31", now),
+            UNIX_EPOCH.checked_sub(Duration::from_secs(86_400))
+        );
+        assert_eq!(parse_time("2023-02-29", now), None);
+        assert_eq!(parse_time("2024-13-01", now), None);
+        assert_eq!(parse_time("2024-01", now), None);
+        assert_eq!(parse_time("2024-01-01T25:00:00", now), None);
+    }
 }
@@ -1,8 +1,13 @@
+#[allow(clippy::single_component_path_imports)]
 use xloc;
 
+mod common;
+
+#[allow(clippy::redundant_static_lifetimes)]
 const DATA_PATH: &'static str = "tests/data";
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn default_app() {
     let app = xloc::App::default();
     assert_eq!(app.get_njobs(), 1);
@@ -10,6 +15,7 @@ fn default_app() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn new_app() {
     let app = xloc::App::new(3, true);
     assert_eq!(app.get_njobs(), 3);
@@ -36,6 +42,32 @@ fn count_words_dir() {
     assert_eq!(result.unwrap(), 120);
 }
 
+#[test]
+fn count_words_dir_word_modes() {
+    let mut app = xloc::App::new(2, true);
+
+    app.set_word_mode(xloc::WordMode::Unicode);
+    assert_eq!(app.count(DATA_PATH).unwrap(), 122);
+
+    // Matches `wc -w`
+    app.set_word_mode(xloc::WordMode::Whitespace);
+    assert_eq!(app.count(DATA_PATH).unwrap(), 121);
+}
+
+#[test]
+fn count_words_dir_custom_definition() {
+    let mut app = xloc::App::new(1, true);
+
+    app.set_word_mode(xloc::WordMode::Identifier);
+    assert_eq!(app.count("tests/data/data.rs").unwrap(), 36);
+
+    app.set_word_mode(xloc::WordMode::Custom(r"\bfn\b".to_string()));
+    assert_eq!(app.count("tests/data/data.rs").unwrap(), 2);
+
+    app.set_word_mode(xloc::WordMode::Custom(r"[".to_string()));
+    assert!(app.count(DATA_PATH).is_err());
+}
+
 #[test]
 fn count_lines_file() {
     let app = xloc::App::default();
@@ -75,6 +107,612 @@ fn count_words_file() {
 }
 
 #[test]
+fn count_non_utf8_files() {
+    let lines = xloc::App::default();
+    let mut words = xloc::App::new(1, true);
+    words.set_word_mode(xloc::WordMode::Whitespace);
+
+    for file in ["latin1", "utf16le", "utf16be", "utf8bom"] {
+        let path = format!("tests/encodings/{}.txt", file);
+        assert_eq!(lines.count(&path).unwrap(), 4, "{}", path);
+        assert_eq!(words.count(&path).unwrap(), 8, "{}", path);
+    }
+
+    assert_eq!(lines.count("tests/encodings").unwrap(), 16);
+}
+
+#[test]
+fn count_skips_binary_files() {
+    let mut app = xloc::App::default();
+
+    let summary = app.summarize("tests/binary").unwrap();
+    assert_eq!(summary.count, 2);
+    assert_eq!(summary.files, 1);
+    assert_eq!(summary.skipped_for(xloc::SkipReason::Binary), 1);
+    assert!(summary.skipped[0].path.ends_with("blob.bin"));
+
+    app.set_binary(true);
+    let summary = app.summarize("tests/binary").unwrap();
+    assert_eq!(summary.files, 2);
+    assert!(summary.skipped.is_empty());
+}
+
+#[test]
+fn summarize_no_skipped() {
+    let app = xloc::App::new(3, false);
+
+    let summary = app.summarize(DATA_PATH).unwrap();
+    assert_eq!(summary.count, 45);
+    assert_eq!(summary.files, 3);
+    assert!(summary.skipped.is_empty());
+}
+
+#[test]
+fn count_compressed_files() {
+    let mut app = xloc::App::new(2, false);
+    app.set_decompress(true);
+
+    let summary = app.summarize("tests/compressed").unwrap();
+    assert_eq!(summary.count, 24);
+    assert_eq!(summary.files, 4);
+    assert_eq!(summary.skipped_for(xloc::SkipReason::Corrupt), 1);
+
+    let report = app.hygiene("tests/compressed").unwrap();
+    assert_eq!(report.languages["Log"].files, 4);
+    assert_eq!(report.languages["Log"].lines, 20);
+}
+
+#[test]
+fn count_archives() {
+    let mut app = xloc::App::new(2, false);
+
+    // Archives are binary, unless we walk them
+    let summary = app.summarize("tests/archives").unwrap();
+    assert_eq!(summary.files, 0);
+    assert_eq!(summary.skipped.len(), 4);
+
+    app.set_archives(true);
+    let summary = app.summarize("tests/archives").unwrap();
+    assert_eq!(summary.files, 8);
+    assert_eq!(summary.count, 32);
+    assert!(summary.skipped.is_empty());
+
+    let entry = std::path::Path::new("tests/archives/release.zip!/release/src/main.rs");
+    assert_eq!(summary.per_file[entry], 4);
+
+    let report = app.hygiene("tests/archives/release.tgz").unwrap();
+    assert_eq!(report.languages["Rust"].files, 1);
+    assert_eq!(report.languages["Markdown"].files, 1);
+}
+
+#[test]
+fn count_archives_scan_rules() {
+    let mut app = xloc::App::new(1, false);
+    app.set_archives(true);
+
+    // Entries are matched like files in a directory named after the archive
+    app.set_include(vec!["*.rs".to_string()]);
+    let summary = app.summarize("tests/archives").unwrap();
+    assert_eq!(summary.files, 4);
+    assert!(summary.per_file.keys().all(|p| p.ends_with("main.rs")));
+
+    app.set_include(vec![]);
+    app.set_exclude(vec!["release.zip!/release/src".to_string()]);
+    let summary = app.summarize("tests/archives").unwrap();
+    assert_eq!(summary.files, 7);
+
+    // tests/archives/release.tar!/release/README.md is 3 deep
+    app.set_exclude(vec![]);
+    app.set_max_depth(Some(2));
+    assert_eq!(app.summarize("tests/archives").unwrap().files, 0);
+    app.set_max_depth(Some(3));
+    assert_eq!(app.summarize("tests/archives").unwrap().files, 4);
+}
+
+#[test]
+fn set_archives() {
+    let mut app = xloc::App::default();
+    assert!(!app.get_archives());
+    assert!(app.set_archives(true));
+    assert!(app.get_archives());
+}
+
+#[test]
+fn count_ignore() {
+    let mut app = xloc::App::default();
+
+    let summary = app.summarize("tests/ignore").unwrap();
+    assert_eq!(summary.files, 3);
+    assert!(summary.per_file.keys().all(|p| !p.ends_with("debug.log")));
+
+    app.set_ignore(false);
+    let summary = app.summarize("tests/ignore").unwrap();
+    assert_eq!(summary.files, 7);
+
+    // Explicit paths are counted, even when ignored
+    app.set_ignore(true);
+    assert_eq!(app.count("tests/ignore/debug.log").unwrap(), 2);
+}
+
+#[test]
+fn set_ignore() {
+    let mut app = xloc::App::default();
+    assert!(app.get_ignore());
+    assert!(!app.set_ignore(false));
+    assert!(!app.get_ignore());
+}
+
+#[test]
+fn count_globs() {
+    let mut app = xloc::App::default();
+    app.set_include(vec!["*.rs".to_string()]);
+
+    let summary = app.summarize("tests/ignore").unwrap();
+    assert_eq!(summary.files, 2);
+
+    app.set_exclude(vec!["nested/".to_string()]);
+    let summary = app.summarize("tests/ignore").unwrap();
+    assert_eq!(summary.files, 1);
+    assert_eq!(summary.count, 4);
+
+    app.set_exclude(vec!["src/{a".to_string()]);
+    let e = app.summarize("tests/ignore").unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn set_include_exclude() {
+    let mut app = xloc::App::default();
+    assert_eq!(app.set_include(vec!["*.rs".to_string()]), vec!["*.rs"]);
+    assert_eq!(app.get_include(), ["*.rs"]);
+    assert_eq!(
+        app.set_exclude(vec!["docs/**".to_string()]),
+        vec!["docs/**"]
+    );
+    assert_eq!(app.get_exclude(), ["docs/**"]);
+}
+
+#[test]
+fn count_default_excludes() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    std::fs::create_dir_all(dir.path().join("node_modules/left-pad")).unwrap();
+    std::fs::write(dir.path().join("node_modules/left-pad/index.js"), "\n").unwrap();
+    std::fs::write(dir.path().join("index.js"), "\n").unwrap();
+
+    let mut app = xloc::App::default();
+    assert_eq!(app.summarize(root).unwrap().files, 1);
+
+    app.set_default_excludes(vec![]);
+    assert_eq!(app.summarize(root).unwrap().files, 2);
+}
+
+#[test]
+fn set_default_excludes() {
+    let mut app = xloc::App::default();
+    assert!(app.get_default_excludes().contains(&"target".to_string()));
+    assert_eq!(app.set_default_excludes(vec![".git".to_string()]), [".git"]);
+    assert_eq!(app.get_default_excludes(), [".git"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn count_symlinks() {
+    use std::os::unix::fs::symlink;
+    use xloc::{SkipReason, SymlinkPolicy};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/lib.rs"), "fn lib() {}\n").unwrap();
+    symlink(dir.path(), dir.path().join("src/parent")).unwrap();
+    symlink(dir.path().join("src"), dir.path().join("alias")).unwrap();
+
+    // alias/ finds src/lib.rs again, which is only counted once, and
+    // both routes hit the loop
+    let mut app = xloc::App::default();
+    let summary = app.summarize(root).unwrap();
+    assert_eq!(summary.files, 1);
+    assert_eq!(summary.skipped_for(SkipReason::SymlinkLoop), 2);
+
+    app.set_symlinks(SymlinkPolicy::Never);
+    let summary = app.summarize(root).unwrap();
+    assert_eq!(summary.files, 1);
+    assert!(summary.skipped.is_empty());
+}
+
+#[test]
+fn set_symlinks() {
+    use xloc::SymlinkPolicy;
+
+    let mut app = xloc::App::default();
+    assert_eq!(
+        app.set_symlinks(SymlinkPolicy::Always),
+        SymlinkPolicy::Always
+    );
+    assert_eq!(app.get_symlinks(), SymlinkPolicy::Always);
+}
+
+#[test]
+fn count_hidden_and_max_depth() {
+    let mut app = xloc::App::default();
+    app.set_hidden(true);
+    assert_eq!(app.summarize("tests/ignore").unwrap().files, 7);
+
+    app.set_max_depth(Some(1));
+    let summary = app.summarize("tests/ignore").unwrap();
+    assert_eq!(summary.files, 5);
+    assert!(summary
+        .per_file
+        .keys()
+        .all(|p| !p.starts_with("tests/ignore/nested")));
+}
+
+#[test]
+fn set_hidden_and_max_depth() {
+    let mut app = xloc::App::default();
+    assert!(app.set_hidden(true));
+    assert!(app.get_hidden());
+    assert_eq!(app.set_max_depth(Some(3)), Some(3));
+    assert_eq!(app.get_max_depth(), Some(3));
+}
+
+#[test]
+fn count_modified() {
+    let dir = tempfile::tempdir().unwrap();
+    let now = std::time::SystemTime::now();
+    let day = std::time::Duration::from_secs(86_400);
+
+    for (name, age) in [("old.rs", 400), ("new.rs", 0)] {
+        let file = std::fs::File::create(dir.path().join(name)).unwrap();
+        file.set_modified(now - day * age).unwrap();
+    }
+
+    let path = dir.path().to_str().unwrap();
+    let mut app = xloc::App::default();
+    assert_eq!(app.summarize(path).unwrap().files, 2);
+
+    assert_eq!(app.set_newer_than(Some(now - day)), Some(now - day));
+    assert_eq!(app.get_newer_than(), Some(now - day));
+    let summary = app.summarize(path).unwrap();
+    assert_eq!(summary.files, 1);
+    assert!(summary.per_file.keys().all(|p| p.ends_with("new.rs")));
+
+    app.set_newer_than(None);
+    assert_eq!(app.set_older_than(Some(now - day)), Some(now - day));
+    assert_eq!(app.get_older_than(), Some(now - day));
+    let summary = app.summarize(path).unwrap();
+    assert!(summary.per_file.keys().all(|p| p.ends_with("old.rs")));
+}
+
+#[test]
+fn count_max_file_size() {
+    let mut app = xloc::App::default();
+    assert_eq!(app.set_max_file_size(Some(300)), Some(300));
+    assert_eq!(app.get_max_file_size(), Some(300));
+
+    // data.py and data.rs are just over 300 bytes
+    let summary = app.summarize("tests/data").unwrap();
+    assert_eq!(summary.files, 1);
+    assert_eq!(summary.skipped_for(xloc::SkipReason::TooLarge), 2);
+
+    app.set_max_file_size(None);
+    assert_eq!(app.summarize("tests/data").unwrap().files, 3);
+}
+
+#[test]
+fn summarize_all_dedupes() {
+    let app = xloc::App::default();
+
+    let summary = app.summarize_all(&["tests", "tests/data"]).unwrap();
+    let tests = app.summarize("tests").unwrap();
+    assert_eq!(summary.count, tests.count);
+    assert_eq!(summary.files, tests.files);
+
+    let roots = summary.per_root.iter().map(|r| r.1).collect::<Vec<_>>();
+    assert_eq!(roots, vec![tests.count, 0]);
+
+    let summary = app.summarize_all(&["tests/data", "tests"]).unwrap();
+    assert_eq!(summary.per_root[0].1, 45);
+    assert_eq!(summary.count, tests.count);
+
+    let report = app
+        .hygiene_all(&["tests/data", "tests/data/data.py"])
+        .unwrap();
+    assert_eq!(report.languages["Python"].files, 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn summarize_hard_links() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_str().unwrap();
+    std::fs::write(dir.path().join("a.rs"), "fn a() {}\n").unwrap();
+    std::fs::hard_link(dir.path().join("a.rs"), dir.path().join("b.rs")).unwrap();
+
+    let summary = xloc::App::default().summarize(root).unwrap();
+    assert_eq!(summary.files, 1);
+    assert_eq!(summary.count, 2);
+}
+
+#[test]
+fn set_one_file_system() {
+    let mut app = xloc::App::default();
+    assert!(app.set_one_file_system(true));
+    assert!(app.get_one_file_system());
+    assert_eq!(app.count("tests/data").unwrap(), 45);
+}
+
+#[test]
+fn summarize_files() {
+    let app = xloc::App::new(3, false);
+    let files = vec![
+        "tests/data/data.rs".into(),
+        "tests/data".into(),
+        "tests/data/./data.rs".into(),
+        "tests/ignore/debug.log".into(),
+    ];
+
+    // The dir is left out, and data.rs is only counted once
+    let summary = app.summarize_files(files).unwrap();
+    assert_eq!(summary.files, 2);
+    assert_eq!(summary.count, 19);
+    assert!(summary.per_root.is_empty());
+
+    let summary = app
+        .summarize_files(vec!["tests/data/vanished.rs".into()])
+        .unwrap();
+    assert_eq!(summary.skipped_for(xloc::SkipReason::Vanished), 1);
+}
+
+#[test]
+fn count_reader() {
+    let mut app = xloc::App::default();
+    let text = "fn main() {\n    println!(\"Hello\");\n}\n";
+    assert_eq!(app.count_reader(text.as_bytes()).unwrap(), 4);
+
+    app.set_words(true);
+    app.set_word_mode(xloc::WordMode::Identifier);
+    assert_eq!(app.count_reader(text.as_bytes()).unwrap(), 4);
+
+    app.set_decompress(true);
+    let gz = std::fs::File::open("tests/compressed/access.log.gz").unwrap();
+    let summary = app.summarize_reader(gz, "access.log.gz").unwrap();
+    assert_eq!(summary.files, 1);
+    assert!(summary
+        .per_file
+        .contains_key(std::path::Path::new("access.log.gz")));
+}
+
+#[test]
+fn count_git_tracked() {
+    let dir = tempfile::tempdir().unwrap();
+    common::stage(
+        dir.path(),
+        &[("src/main.rs", "fn main() {}\n"), ("src/lib.rs", "\n")],
+    );
+    std::fs::write(dir.path().join("scratch.rs"), "\n\n\n").unwrap();
+
+    let root = dir.path().to_str().unwrap();
+    let mut app = xloc::App::default();
+    assert_eq!(app.summarize(root).unwrap().files, 3);
+
+    app.set_git_tracked(true);
+    let summary = app.summarize(root).unwrap();
+    assert_eq!(summary.files, 2);
+    assert_eq!(summary.count, 4);
+
+    app.set_exclude(vec!["lib.rs".to_string()]);
+    assert_eq!(app.summarize(root).unwrap().files, 1);
+
+    let outside = tempfile::tempdir().unwrap();
+    let e = app.summarize(outside.path().to_str().unwrap()).unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn set_git_tracked() {
+    let mut app = xloc::App::default();
+    assert!(app.set_git_tracked(true));
+    assert!(app.get_git_tracked());
+}
+
+#[test]
+fn count_rev() {
+    let dir = tempfile::tempdir().unwrap();
+    common::commit(
+        dir.path(),
+        &[("src/main.rs", "fn main() {}\n"), ("src/lib.rs", "\n\n")],
+        Some("v1.0.0"),
+    );
+
+    // The working tree has moved on since the tag
+    std::fs::remove_file(dir.path().join("src/lib.rs")).unwrap();
+    std::fs::write(dir.path().join("src/main.rs"), "\n\n\n\n\n").unwrap();
+
+    let root = dir.path().to_str().unwrap();
+    let src = dir.path().join("src");
+    let mut app = xloc::App::new(2, false);
+    app.set_rev(Some("v1.0.0".to_string()));
+
+    let summary = app.summarize(root).unwrap();
+    assert_eq!(summary.files, 2);
+    assert_eq!(summary.count, 5);
+
+    // Overlapping paths still count each file in the tree once
+    let summary = app.summarize_all(&[root, src.to_str().unwrap()]).unwrap();
+    assert_eq!(summary.files, 2);
+
+    app.set_include(vec!["lib.rs".to_string()]);
+    assert_eq!(app.summarize(root).unwrap().count, 3);
+
+    app.set_rev(Some("v9.9.9".to_string()));
+    let e = app.summarize(root).unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn set_rev() {
+    let mut app = xloc::App::default();
+    assert_eq!(
+        app.set_rev(Some("HEAD~1".to_string())),
+        Some("HEAD~1".to_string())
+    );
+    assert_eq!(app.get_rev(), Some("HEAD~1"));
+    assert_eq!(app.set_rev(None), None);
+}
+
+#[test]
+fn diff() {
+    let old = tempfile::tempdir().unwrap();
+    let new = tempfile::tempdir().unwrap();
+    std::fs::write(old.path().join("main.rs"), "fn main() {\n}\n").unwrap();
+    std::fs::write(old.path().join("gone.rs"), "// gone\n").unwrap();
+    std::fs::write(new.path().join("main.rs"), "fn main() {\n\n    run();\n}\n").unwrap();
+    std::fs::write(new.path().join("blob.bin"), [0u8, 1, 2]).unwrap();
+
+    let app = xloc::App::default();
+    let report = app
+        .diff(old.path().to_str().unwrap(), new.path().to_str().unwrap())
+        .unwrap();
+
+    let main = &report.files[std::path::Path::new("main.rs")];
+    assert_eq!(main.code.added, 1);
+    assert_eq!(main.blanks.added, 1);
+    assert_eq!(report.languages["Rust"].files, 2);
+    assert_eq!(report.languages["Rust"].comments.net(), -1);
+    assert_eq!(report.total.code.net(), 1);
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].reason, xloc::SkipReason::Binary);
+}
+
+#[test]
+fn history() {
+    let dir = tempfile::tempdir().unwrap();
+
+    for text in ["fn a() {}\n", "fn a() {}\nfn b() {}\n"] {
+        let files = [("lib.rs", text), ("notes.txt", "one two\n")];
+        common::commit(dir.path(), &files, None);
+    }
+
+    let root = dir.path().to_str().unwrap();
+    let mut app = xloc::App::default();
+    let history = app.history(root, "HEAD", xloc::Sampling::Commit).unwrap();
+    assert_eq!(history.points.len(), 2);
+    assert_eq!(history.points[0].languages["Rust"], 2);
+    assert_eq!(history.points[1].languages["Rust"], 3);
+    assert_eq!(history.points[1].total(), 5);
+
+    app.set_words(true);
+    app.set_include(vec!["*.txt".to_string()]);
+    let history = app.history(root, "HEAD", xloc::Sampling::Commit).unwrap();
+    assert_eq!(history.points[1].total(), 2);
+}
+
+#[test]
+fn blame() {
+    let dir = tempfile::tempdir().unwrap();
+    common::commit(
+        dir.path(),
+        &[
+            ("lib.rs", "fn a() {}\n"),
+            ("run.py", "a = 1\n"),
+            (
+                ".mailmap",
+                "Ada Lovelace <ada@example.org> <ada@example.com>\n",
+            ),
+        ],
+        None,
+    );
+
+    std::fs::write(dir.path().join("lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+
+    let root = dir.path().to_str().unwrap();
+    let mut app = xloc::App::new(2, false);
+    let authorship = app.blame(root).unwrap();
+    assert_eq!(authorship.total, 3);
+    assert_eq!(authorship.authors["Ada <ada@example.com>"], 2);
+    assert_eq!(authorship.authors["Not Committed Yet"], 1);
+    assert_eq!(authorship.languages["Python"]["Ada <ada@example.com>"], 1);
+
+    app.set_mailmap(true);
+    let authorship = app.blame(root).unwrap();
+    assert_eq!(authorship.authors["Ada Lovelace <ada@example.org>"], 2);
+
+    let outside = tempfile::tempdir().unwrap();
+    let e = app.blame(outside.path().to_str().unwrap()).unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn set_mailmap() {
+    let mut app = xloc::App::default();
+    assert!(app.set_mailmap(true));
+    assert!(app.get_mailmap());
+}
+
+#[test]
+fn diff_staged_unstaged() {
+    let dir = tempfile::tempdir().unwrap();
+    common::stage(
+        dir.path(),
+        &[
+            ("lib.rs", &b"fn a() {}\n"[..]),
+            ("blob.bin", &[0u8, 1, 2][..]),
+        ],
+    );
+    std::fs::write(dir.path().join("lib.rs"), "fn a() {}\n\n").unwrap();
+
+    // Before the first commit, everything in the index is staged
+    let root = dir.path().to_str().unwrap();
+    let app = xloc::App::default();
+    let staged = app.diff_staged(root).unwrap();
+    assert_eq!(staged.total.code.added, 1);
+    assert_eq!(staged.skipped[0].reason, xloc::SkipReason::Binary);
+
+    let unstaged = app.diff_unstaged(root).unwrap();
+    assert_eq!(unstaged.total.code.added, 0);
+    assert_eq!(unstaged.total.blanks.added, 1);
+}
+
+#[test]
+fn set_decompress() {
+    let mut app = xloc::App::default();
+    assert!(!app.get_decompress());
+    assert!(app.set_decompress(true));
+    assert!(app.get_decompress());
+}
+
+#[test]
+fn set_binary() {
+    let mut app = xloc::App::default();
+    assert!(!app.get_binary());
+    assert!(app.set_binary(true));
+    assert!(app.get_binary());
+}
+
+#[test]
+fn hygiene_dir() {
+    let app = xloc::App::new(2, false);
+
+    let report = app.hygiene(DATA_PATH).unwrap();
+    assert_eq!(report.files.len(), 3);
+    assert_eq!(report.languages.len(), 3);
+    assert_eq!(report.languages["Python"].space_indented, 9);
+    assert_eq!(report.languages["Text"].max_indent, 0);
+    assert_eq!(report.total.lines, 42);
+    assert_eq!(report.total.trailing_whitespace, 0);
+    assert_eq!(report.total.mixed_files, 0);
+}
+
+#[test]
+fn hygiene_bad_path() {
+    let app = xloc::App::default();
+    assert!(app.hygiene("fake_dir").is_err());
+}
+
+#[test]
+#[allow(clippy::useless_vec)]
 fn count_lines_dir_bad_path() {
     let app = xloc::App::default();
     let base_path = String::from("fake_dir");
@@ -100,6 +738,7 @@ fn set_njobs() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn set_words() {
     let mut app = xloc::App::default();
     assert_eq!(app.get_words(), false);
@@ -2,6 +2,9 @@ use assert_cmd::prelude::*;
 use predicates::prelude::*;
 use std::process::Command;
 
+mod common;
+
+#[allow(clippy::redundant_static_lifetimes)]
 const DATA_PATH: &'static str = "tests/data";
 
 #[test]
@@ -55,6 +58,744 @@ fn with_jobs_valid_dir_words() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn word_mode_implies_words() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--word-mode")
+        .arg("whitespace")
+        .arg(DATA_PATH)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("121"));
+
+    Ok(())
+}
+
+#[test]
+fn invalid_word_mode() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--word-mode")
+        .arg("sentences")
+        .arg(DATA_PATH)
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn word_regex() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--word-regex")
+        .arg(r"\d+")
+        .arg("tests/data/data.txt")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("2\n"));
+
+    Ok(())
+}
+
+#[test]
+fn invalid_word_regex() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--word-regex")
+        .arg("(oops")
+        .arg(DATA_PATH)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Invalid word pattern"));
+
+    Ok(())
+}
+
+#[test]
+fn skipped_binaries() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("tests/binary")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("2\n"))
+        .stderr(predicate::str::diff("skipped 1 file(s): binary\n"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--binary")
+        .arg("tests/binary")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn decompress() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("-z")
+        .arg("--word-mode")
+        .arg("whitespace")
+        .arg("tests/compressed/access.log.xz")
+        .arg("tests/compressed/access.log.bz2")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("100\n"));
+
+    Ok(())
+}
+
+#[test]
+fn archives_per_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--archives")
+        .arg("--per-file")
+        .arg("tests/archives/release.tar")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff(
+            "       4 tests/archives/release.tar!/release/README.md\n       \
+             4 tests/archives/release.tar!/release/src/main.rs\n\
+             8\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn archives_include() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--archives")
+        .arg("--per-file")
+        .arg("--include")
+        .arg("*.rs")
+        .arg("tests/archives/release.tar")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff(
+            "       4 tests/archives/release.tar!/release/src/main.rs\n\
+             4\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn no_ignore() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("tests/ignore")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("8\n"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--no-ignore")
+        .arg("tests/ignore")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("16\n"));
+
+    Ok(())
+}
+
+#[test]
+fn include_exclude() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--no-ignore")
+        .arg("--include")
+        .arg("*.rs")
+        .arg("--exclude")
+        .arg("build")
+        .arg("--exclude")
+        .arg("vendor/**")
+        .arg("tests/ignore")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("8\n"));
+
+    Ok(())
+}
+
+#[test]
+fn list_default_excludes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--list-default-excludes")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(".git\n"))
+        .stdout(predicate::str::contains("\ntarget\n"));
+
+    Ok(())
+}
+
+#[test]
+fn default_excludes() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+
+    for name in ["target", "vendor", "src"] {
+        std::fs::create_dir(dir.path().join(name))?;
+        std::fs::write(dir.path().join(name).join("lib.rs"), "fn a() {}\n")?;
+    }
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--per-file")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("vendor/lib.rs"))
+        .stdout(predicate::str::contains("target/lib.rs").not());
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    // Only the names given are left out, so target is counted again
+    cmd.arg("--per-file")
+        .arg("--default-excludes")
+        .arg("vendor,src")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("target/lib.rs"))
+        .stdout(predicate::str::contains("vendor/lib.rs").not())
+        .stdout(predicate::str::contains("src/lib.rs").not());
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--default-excludes")
+        .arg("vendor")
+        .arg("--no-default-excludes")
+        .arg(dir.path())
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn hidden_max_depth() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    // .gitignore, .ignore, .xlocignore, main.rs and keep.log
+    cmd.arg("--hidden")
+        .arg("--max-depth")
+        .arg("1")
+        .arg("tests/ignore")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("13\n"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--hidden")
+        .arg("--no-hidden")
+        .arg("--max-depth")
+        .arg("1")
+        .arg("tests/ignore")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("6\n"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+    cmd.arg("--max-depth")
+        .arg("one")
+        .arg(".")
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn overlapping_paths() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--per-root")
+        .arg("tests/data")
+        .arg("tests/data/data.rs")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff(
+            "      45 tests/data\n       0 tests/data/data.rs\n45\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn files_from() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("xloc")?;
+
+    cmd.arg("--files-from")
+        .arg("-")
+        .write_stdin("tests/data/data.rs\r\ntests/ignore/debug.log\n\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("19\n"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("xloc")?;
+
+    cmd.arg("--files-from")
+        .arg("-")
+        .write_stdin("tests/data/data.rs\0tests/ignore/debug.log\0")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("19\n"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("xloc")?;
+
+    cmd.arg("--files-from")
+        .arg("tests/missing.txt")
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("xloc")?;
+
+    cmd.arg("-w")
+        .arg("--word-mode")
+        .arg("whitespace")
+        .write_stdin("one two\nthree\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("3\n"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("xloc")?;
+
+    cmd.arg("--per-root")
+        .arg("tests/data/data.rs")
+        .arg("-")
+        .write_stdin("one\ntwo\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff(
+            "      17 tests/data/data.rs\n       3 -\n20\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn git_tracked_outside_repo() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--git-tracked").arg(dir.path()).assert().code(1);
+
+    Ok(())
+}
+
+#[test]
+fn rev_not_found() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    git2::Repository::init(dir.path())?;
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--rev")
+        .arg("v1.0.0")
+        .arg(dir.path())
+        .assert()
+        .code(1);
+
+    Ok(())
+}
+
+#[test]
+fn dirs_named_like_modes() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("diff"))?;
+    std::fs::create_dir(dir.path().join("history"))?;
+    std::fs::write(dir.path().join("diff/main.rs"), "a\n")?;
+    std::fs::write(dir.path().join("history/main.rs"), "a\nb\n")?;
+
+    // A path that is exactly a subcommand's name is the subcommand, so
+    // the directory is named in a way the subcommand never is
+    for args in [&["./diff"][..], &["--", "diff"], &["--per-root", "./diff"]] {
+        let mut cmd = Command::cargo_bin("xloc")?;
+        cmd.current_dir(dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout(predicate::str::ends_with("2\n"));
+    }
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+    cmd.current_dir(dir.path())
+        .arg("dif")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("No such file"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+    cmd.current_dir(dir.path())
+        .arg("./history")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("3\n"));
+
+    Ok(())
+}
+
+#[test]
+fn diff_dirs() -> Result<(), Box<dyn std::error::Error>> {
+    let old = tempfile::tempdir()?;
+    let new = tempfile::tempdir()?;
+    std::fs::write(old.path().join("main.rs"), "fn main() {}\n")?;
+    std::fs::write(new.path().join("main.rs"), "// Entry\nfn main() {}\n")?;
+    std::fs::write(new.path().join("lib.py"), "x = 1\ny = 2\n")?;
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("diff")
+        .arg(old.path())
+        .arg(new.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("main.rs"))
+        .stdout(predicate::str::is_match(
+            r"\nRust +1 +0 +0 +\+0 +1 +0 +\+1",
+        )?)
+        .stdout(predicate::str::is_match(r"\nTotal +2 +2 +0 +\+2")?);
+
+    Ok(())
+}
+
+#[test]
+fn diff_revs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+
+    // The binary file is the same blob in both, so it is never read
+    for (tag, text) in [("v1", "fn a() {}\n"), ("v2", "fn a() {}\nfn b() {}\n")] {
+        let files = [("lib.rs", text.as_bytes()), ("blob.bin", &[0u8, 1, 2])];
+        common::commit(dir.path(), &files, Some(tag));
+    }
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.current_dir(dir.path())
+        .arg("--show-skipped")
+        .arg("diff")
+        .arg("v1")
+        .arg("v2")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"\nRust +1 +1 +0 +\+1 ")?)
+        .stderr(predicate::str::is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn history() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let id = common::commit(dir.path(), &[("lib.rs", "fn a() {}\n")], None);
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.current_dir(dir.path())
+        .arg("history")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "commit,date,tag,language,count\n",
+        ))
+        .stdout(predicate::str::contains(format!("{},", id)))
+        .stdout(predicate::str::ends_with(",,Rust,2\n"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.current_dir(dir.path())
+        .args(["history", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\"languages\": {\"Rust\": 2}, \"total\": 2}",
+        ));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.current_dir(dir.path())
+        .args([
+            "--max-file-size",
+            "4",
+            "--show-skipped",
+            "--fail-on-skipped",
+        ])
+        .arg("history")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::diff("commit,date,tag,language,count\n"))
+        .stderr(predicate::str::contains("lib.rs: too large"));
+
+    Ok(())
+}
+
+#[test]
+fn blame_uncommitted() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    git2::Repository::init(dir.path())?;
+    std::fs::write(dir.path().join("lib.rs"), "fn a() {}\n")?;
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--blame")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            r"\nNot Committed Yet +1 +100.0%\n",
+        )?)
+        .stdout(predicate::str::is_match(r"\nRust +Not Committed Yet +1\n")?);
+
+    Ok(())
+}
+
+#[test]
+fn diff_staged() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    common::stage(dir.path(), &[("lib.rs", "fn a() {}\n")]);
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.current_dir(dir.path())
+        .args(["diff", "--staged"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"\nlib.rs +1 +1 +0 +\+1 ")?);
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.args(["diff", "--staged", "v1"]).assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn show_skipped() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--show-skipped")
+        .arg("tests/binary")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("blob.bin: binary"));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::symlink;
+
+    let dir = tempfile::tempdir()?;
+    let root = dir.path().join("root");
+    let other = dir.path().join("other");
+    std::fs::create_dir_all(root.join("sub"))?;
+    std::fs::create_dir_all(&other)?;
+    std::fs::write(root.join("sub/a.rs"), "a\n")?;
+    std::fs::write(other.join("b.rs"), "b\n")?;
+    symlink(&root, root.join("sub/loop"))?;
+    symlink(root.join("sub/a.rs"), root.join("link.rs"))?;
+    symlink(&other, root.join("other"))?;
+
+    // Every symlink is followed by default, skipping the loop, and
+    // link.rs is counted once with the file it points to
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--show-skipped")
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("4\n"))
+        .stderr(predicate::str::contains("loop: symlink loop"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--show-skipped")
+        .arg("--symlinks")
+        .arg("within-root")
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("2\n"))
+        .stderr(predicate::str::contains("loop: symlink loop"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--show-skipped")
+        .arg("--symlinks")
+        .arg("never")
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("2\n"))
+        .stderr(predicate::str::is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn newer_and_older_than() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("old.rs"), "a\nb\n")?;
+    std::fs::write(dir.path().join("new.rs"), "a\n")?;
+
+    let old = std::fs::File::options()
+        .write(true)
+        .open(dir.path().join("old.rs"))?;
+    old.set_modified(std::time::UNIX_EPOCH)?;
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+    cmd.arg("--newer-than")
+        .arg("30d")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("2\n"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+    cmd.arg("--older-than")
+        .arg("2000-01-01")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("3\n"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+    cmd.arg("--newer-than")
+        .arg("yesterday")
+        .arg(".")
+        .assert()
+        .failure();
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+    cmd.arg("--newer-than")
+        .arg("30d")
+        .arg("--rev")
+        .arg("HEAD")
+        .arg(".")
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn max_file_size() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--show-skipped")
+        .arg("--max-file-size")
+        .arg("1K")
+        .arg("tests/data")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--show-skipped")
+        .arg("--max-file-size")
+        .arg("300")
+        .arg("tests/data")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("data.rs: too large"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+    cmd.arg("--max-file-size")
+        .arg("big")
+        .arg(".")
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn fail_on_skipped() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--fail-on-skipped")
+        .arg("tests/binary")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::diff("2\n"));
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--fail-on-skipped")
+        .arg(DATA_PATH)
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--hygiene")
+        .arg("--show-skipped")
+        .arg("--fail-on-skipped")
+        .arg("tests/binary")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("notes.txt"))
+        .stderr(predicate::str::contains("blob.bin: binary"));
+
+    Ok(())
+}
+
+#[test]
+fn hygiene_report() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("xloc")?;
+
+    cmd.arg("--hygiene")
+        .arg(DATA_PATH)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("tests/data/data.rs"))
+        .stdout(predicate::str::contains("Python"))
+        .stdout(predicate::str::contains("Total"));
+
+    Ok(())
+}
+
+#[test]
+fn hygiene_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("xloc")?;
+
+    cmd.arg("--hygiene")
+        .write_stdin("one  \n\ttwo\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("-"))
+        .stdout(predicate::str::contains("Total"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("xloc")?;
+
+    cmd.arg("--blame")
+        .arg("-")
+        .write_stdin("one\n")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("--blame can't read from stdin"));
+
+    Ok(())
+}
+
 #[test]
 fn no_jobs_multiple_path_args() -> Result<(), Box<dyn std::error::Error>> {
     let mut cmd = Command::cargo_bin("xloc")?;
@@ -0,0 +1,47 @@
+use std::fs;
+use std::path::Path;
+
+use git2::{Oid, Repository, Signature};
+
+/// Writes `files` into the repository at `dir` and adds them to its
+/// index, creating the repository first if needed.
+pub fn stage<C: AsRef<[u8]>>(dir: &Path, files: &[(&str, C)]) -> Repository {
+    let repo = Repository::open(dir)
+        .or_else(|_| Repository::init(dir))
+        .unwrap();
+    let mut index = repo.index().unwrap();
+
+    for (name, contents) in files {
+        let file = dir.join(name);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, contents).unwrap();
+        index.add_path(Path::new(name)).unwrap();
+    }
+
+    index.write().unwrap();
+    repo
+}
+
+/// Writes and stages `files` like [stage], then commits them on top of
+/// `HEAD`, tagging the commit as `tag` if given.
+pub fn commit<C: AsRef<[u8]>>(dir: &Path, files: &[(&str, C)], tag: Option<&str>) -> Oid {
+    let repo = stage(dir, files);
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let author = Signature::now("Ada", "ada@example.com").unwrap();
+    let parent = repo.head().ok().map(|h| h.peel_to_commit().unwrap());
+    let parents = parent.iter().collect::<Vec<_>>();
+    let message = tag.unwrap_or("work");
+
+    let id = repo
+        .commit(Some("HEAD"), &author, &author, message, &tree, &parents)
+        .unwrap();
+
+    if let Some(tag) = tag {
+        let object = repo.find_object(id, None).unwrap();
+        repo.tag_lightweight(tag, &object, false).unwrap();
+    }
+
+    id
+}